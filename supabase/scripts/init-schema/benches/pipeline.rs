@@ -0,0 +1,118 @@
+//! Throughput of parsing a dump and writing the files, on synthetic dumps of growing size.
+//!
+//! Run with `cargo bench --bench pipeline`, or `cargo bench --bench pipeline -- /10000` to
+//! profile a single size.
+
+use std::path::Path;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use init_schema::layout::DefaultLayout;
+use init_schema::options::Options;
+use init_schema::parse::get_nodes;
+use init_schema::trace::Tracer;
+use init_schema::write::{MemorySink, write_nodes_to};
+
+/// Number of tables of each dump. Every table comes with a primary key, an index, a function and
+/// grants.
+const SIZES: &[usize] = &[100, 1_000, 10_000];
+
+/// A dump with `tables` tables spread over a few schemas, in the order `pg_dump` writes them
+fn synthetic_dump(tables: usize) -> String {
+    let schemas = ["public", "api", "private"];
+    let mut dump = String::from("SET statement_timeout = 0;\nSET client_encoding = 'UTF8';\n\n");
+
+    schemas
+        .iter()
+        .filter(|s| **s != "public")
+        .for_each(|s| dump.push_str(&format!("CREATE SCHEMA {s};\n\n")));
+
+    (0..tables).for_each(|i| {
+        let schema = schemas[i % schemas.len()];
+        dump.push_str(&format!(
+            "CREATE FUNCTION {schema}.fn_{i}(p integer) RETURNS integer\n    LANGUAGE sql\n    AS $$ SELECT p + {i} $$;\n\n"
+        ));
+        dump.push_str(&format!(
+            "CREATE TABLE {schema}.table_{i} (\n    id integer NOT NULL,\n    name text,\n    created_at timestamp with time zone DEFAULT now()\n);\n\n"
+        ));
+    });
+
+    (0..tables).for_each(|i| {
+        let schema = schemas[i % schemas.len()];
+        dump.push_str(&format!(
+            "ALTER TABLE ONLY {schema}.table_{i}\n    ADD CONSTRAINT table_{i}_pkey PRIMARY KEY (id);\n\n"
+        ));
+        dump.push_str(&format!("CREATE INDEX table_{i}_name_idx ON {schema}.table_{i} USING btree (name);\n\n"));
+    });
+
+    (0..tables).for_each(|i| {
+        let schema = schemas[i % schemas.len()];
+        dump.push_str(&format!(
+            "GRANT ALL ON FUNCTION {schema}.fn_{i}(p integer) TO authenticated;\n"
+        ));
+        dump.push_str(&format!("GRANT ALL ON TABLE {schema}.table_{i} TO anon;\n"));
+        dump.push_str(&format!(
+            "GRANT ALL ON TABLE {schema}.table_{i} TO authenticated;\n"
+        ));
+    });
+
+    dump
+}
+
+fn write(dump: &str, options: &Options) -> MemorySink {
+    let nodes = get_nodes(dump, options);
+    let mut sink = MemorySink::default();
+    write_nodes_to(
+        &nodes,
+        Path::new("schemas"),
+        options,
+        &DefaultLayout,
+        &mut Tracer::default(),
+        &mut sink,
+    );
+    sink
+}
+
+fn pipeline(c: &mut Criterion) {
+    let options = Options::default();
+    let mut group = c.benchmark_group("pipeline");
+    group.sample_size(10);
+
+    SIZES.iter().for_each(|&size| {
+        let dump = synthetic_dump(size);
+
+        // The output must not depend on anything but the dump, otherwise the numbers of two runs
+        // are not comparable
+        let files = write(&dump, &options).files;
+        assert_eq!(
+            files,
+            write(&dump, &options).files,
+            "Output of {} tables is not stable",
+            size
+        );
+
+        group.throughput(Throughput::Bytes(dump.len() as u64));
+        group.bench_with_input(BenchmarkId::new("get_nodes", size), &dump, |b, dump| {
+            b.iter(|| get_nodes(dump, &options))
+        });
+
+        let nodes = get_nodes(&dump, &options);
+        group.bench_with_input(BenchmarkId::new("write_nodes", size), &nodes, |b, nodes| {
+            b.iter(|| {
+                let mut sink = MemorySink::default();
+                write_nodes_to(
+                    nodes,
+                    Path::new("schemas"),
+                    &options,
+                    &DefaultLayout,
+                    &mut Tracer::default(),
+                    &mut sink,
+                )
+            })
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
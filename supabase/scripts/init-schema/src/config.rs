@@ -0,0 +1,65 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+/// Name of the configuration file in the Supabase directory, next to `config.toml`
+pub const CONFIG_FILE: &str = ".init-schema.toml";
+
+/// Keys of the configuration file, to warn about unknown ones
+const KEYS: &[&str] = &[
+    "schemas",
+    "output_dir",
+    "skip_unsupported",
+    "layout",
+    "format_cmd",
+    "skip_settings",
+];
+
+/// How the files are laid out, like `--flat` and `--bucket-by-initial`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// One file per object
+    Default,
+    /// A single file per schema
+    Flat,
+    /// A directory level named after the first character of every object
+    BucketByInitial,
+}
+
+/// Options that are read from `.init-schema.toml`, so that every run of a project uses the same.
+/// Flags on the command line take precedence.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    pub schemas: Option<Vec<String>>,
+    /// Relative paths are resolved against the Supabase directory, like `--output-dir`
+    pub output_dir: Option<PathBuf>,
+    pub skip_unsupported: Option<bool>,
+    pub layout: Option<Layout>,
+    pub format_cmd: Option<String>,
+    /// Variables whose `SET` and `RESET` statements are left out, like `--skip-settings`
+    pub skip_settings: Option<Vec<String>>,
+}
+
+/// Read the configuration file of the Supabase directory, if there is one. Unknown keys are
+/// logged and ignored.
+pub fn load(supabase_dir: &Path) -> Option<Config> {
+    let path = supabase_dir.join(CONFIG_FILE);
+    let content = fs::read_to_string(&path).ok()?;
+
+    let table = content
+        .parse::<toml::Table>()
+        .unwrap_or_else(|e| panic!("Failed to parse {}: {}", path.display(), e));
+    table
+        .keys()
+        .filter(|key| !KEYS.contains(&key.as_str()))
+        .for_each(|key| log::warn!("Ignoring unknown key '{}' in {}", key, path.display()));
+
+    let config = table
+        .try_into()
+        .unwrap_or_else(|e| panic!("Invalid {}: {}", path.display(), e));
+    Some(config)
+}
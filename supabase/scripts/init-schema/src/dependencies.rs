@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+
+use pg_query::{Node, NodeEnum, NodeRef};
+
+use crate::locations::StatementLocation;
+use crate::parse::{get_sval, schema_or_public};
+
+/// Category, schema and name of an object that other statements can reference
+type Object<'a> = (&'static str, &'a str, &'a str);
+
+/// Categories of statements that create an object other statements can depend on
+const OBJECT_CATEGORIES: &[&str] = &[
+    "Table",
+    "Partition",
+    "View",
+    "Function",
+    "TriggerFunction",
+    "EnumNode",
+    "CompositeType",
+    "Aggregate",
+    "Sequence",
+    "Collation",
+    "TextSearch",
+    "ForeignTable",
+];
+
+/// The SQL of `target` preceded by everything it depends on, e.g. the enum of a column or the
+/// table a foreign key references, so that it can be applied on its own. Foreign keys come last,
+/// so that tables that reference each other can be created.
+pub fn self_contained_sql(target: &StatementLocation, nodes: &[StatementLocation]) -> String {
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+
+    // A policy is no object of its own, but depends on its table and the functions it calls, e.g.
+    // `auth.uid()`, so those come first and the policy last
+    if let StatementLocation::Policy(policy) = target {
+        let mut references = policy.functions.iter().cloned().collect::<HashSet<_>>();
+        references.insert((policy.schema.clone(), policy.table.clone()));
+        dependencies(&references, nodes)
+            .into_iter()
+            .for_each(|dependency| visit(dependency, nodes, &mut visited, &mut order));
+
+        let mut sql = statements_in_order(&order, nodes);
+        sql.push(target.sql().trim().to_string());
+        return format!("{}\n", sql.join("\n\n"));
+    }
+
+    let Some(object) = object(target) else {
+        return format!("{}\n", target.sql().trim());
+    };
+    visit(object, nodes, &mut visited, &mut order);
+
+    format!("{}\n", statements_in_order(&order, nodes).join("\n\n"))
+}
+
+/// The distinct statements of all objects in `order`. Foreign keys come last.
+fn statements_in_order(order: &[Object<'_>], nodes: &[StatementLocation]) -> Vec<String> {
+    let mut statements = order
+        .iter()
+        .flat_map(|o| {
+            let mut members = statements_of(o, nodes);
+            // A drop or rename keeps the statements of the object in the order of the dump
+            if !members.iter().any(|n| n.keeps_dump_order()) {
+                members.sort_by_cached_key(|n| n.sort_key());
+            }
+            members
+        })
+        .collect::<Vec<_>>();
+    statements.sort_by_key(|n| matches!(n.object(), StatementLocation::ForeignKey(_)));
+
+    let mut sql: Vec<String> = Vec::new();
+    statements.iter().for_each(|n| {
+        let statement = n.sql().trim().to_string();
+        if !sql.contains(&statement) {
+            sql.push(statement);
+        }
+    });
+
+    sql
+}
+
+/// Add the dependencies of `current` to `order` depth first, followed by the object itself
+fn visit<'a>(
+    current: Object<'a>,
+    nodes: &'a [StatementLocation],
+    visited: &mut HashSet<Object<'a>>,
+    order: &mut Vec<Object<'a>>,
+) {
+    if !visited.insert(current) {
+        return;
+    }
+
+    let references = statements_of(&current, nodes)
+        .iter()
+        .flat_map(|n| references(&n.sql()))
+        .collect::<HashSet<_>>();
+
+    dependencies(&references, nodes)
+        .into_iter()
+        .for_each(|dependency| visit(dependency, nodes, visited, order));
+
+    order.push(current);
+}
+
+/// The objects of the dump with one of the referenced schemas and names
+fn dependencies<'a>(
+    references: &HashSet<(String, String)>,
+    nodes: &'a [StatementLocation],
+) -> Vec<Object<'a>> {
+    nodes
+        .iter()
+        .filter_map(object)
+        .filter(|(_, schema, name)| references.contains(&(schema.to_string(), name.to_string())))
+        .collect()
+}
+
+/// The object a statement creates or changes, if other statements can reference it
+fn object(node: &StatementLocation) -> Option<Object<'_>> {
+    let category = node.category();
+    if !OBJECT_CATEGORIES.contains(&category) {
+        return None;
+    }
+
+    Some((category, node.schema()?, node.name()))
+}
+
+/// All statements that belong to an object. The foreign keys and sequences of a table are part
+/// of it, as its definition is not complete without them.
+fn statements_of<'a>(
+    object: &Object<'_>,
+    nodes: &'a [StatementLocation],
+) -> Vec<&'a StatementLocation> {
+    let (category, schema, name) = *object;
+
+    // Only some statements of a sequence name the table that owns it
+    let sequences = nodes
+        .iter()
+        .filter_map(|n| match n {
+            StatementLocation::Sequence(s)
+                if category == "Table" && s.owner() == Some((schema, name)) =>
+            {
+                Some((s.schema.as_str(), s.name.as_str()))
+            }
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    nodes
+        .iter()
+        .filter(|n| match n.object() {
+            StatementLocation::ForeignKey(fk) => {
+                category == "Table" && fk.source_schema == schema && fk.source_table == name
+            }
+            StatementLocation::Sequence(s)
+                if sequences.contains(&(s.schema.as_str(), s.name.as_str())) =>
+            {
+                true
+            }
+            _ => n.category() == category && n.schema() == Some(schema) && n.name() == name,
+        })
+        .collect()
+}
+
+/// Schema and name of every relation, type and function a statement references. Unqualified
+/// names are assumed to be in `public`.
+fn references(sql: &str) -> Vec<(String, String)> {
+    let Ok(result) = pg_query::parse(sql) else {
+        return Vec::new();
+    };
+
+    // The node iterator of pg_query covers queries, but not the column and parameter definitions
+    // of DDL statements, so those are walked here
+    let mut references = Vec::new();
+    result
+        .protobuf
+        .nodes()
+        .into_iter()
+        .for_each(|(node, ..)| match node {
+            NodeRef::RangeVar(r) => {
+                references.push((schema_or_public(&r.schemaname), r.relname.clone()))
+            }
+            NodeRef::FuncCall(f) => references.extend(qualified_name(&f.funcname)),
+            NodeRef::TypeCast(c) => {
+                references.extend(c.type_name.as_ref().and_then(|t| qualified_name(&t.names)))
+            }
+            NodeRef::CreateStmt(s) => {
+                s.table_elts
+                    .iter()
+                    .chain(&s.constraints)
+                    .for_each(|n| definition_references(n, &mut references));
+                // The parents of `INHERITS` and `PARTITION OF`
+                s.inh_relations.iter().for_each(|n| {
+                    if let Some(NodeEnum::RangeVar(r)) = &n.node {
+                        references.push((schema_or_public(&r.schemaname), r.relname.clone()));
+                    }
+                });
+            }
+            NodeRef::CompositeTypeStmt(s) => s
+                .coldeflist
+                .iter()
+                .for_each(|n| definition_references(n, &mut references)),
+            NodeRef::AlterTableStmt(s) => s.cmds.iter().for_each(|cmd| {
+                if let Some(NodeEnum::AlterTableCmd(c)) = &cmd.node
+                    && let Some(def) = &c.def
+                {
+                    definition_references(def, &mut references);
+                }
+            }),
+            NodeRef::CreateFunctionStmt(f) => {
+                f.parameters.iter().for_each(|p| {
+                    if let Some(NodeEnum::FunctionParameter(p)) = &p.node {
+                        references
+                            .extend(p.arg_type.as_ref().and_then(|t| qualified_name(&t.names)));
+                    }
+                });
+                references.extend(
+                    f.return_type
+                        .as_ref()
+                        .and_then(|t| qualified_name(&t.names)),
+                );
+            }
+            NodeRef::CreateTrigStmt(t) => references.extend(qualified_name(&t.funcname)),
+            _ => {}
+        });
+
+    references
+}
+
+/// References of a column or constraint definition, i.e. the column type and the table of a
+/// foreign key
+fn definition_references(node: &Node, references: &mut Vec<(String, String)>) {
+    match &node.node {
+        Some(NodeEnum::ColumnDef(c)) => {
+            references.extend(c.type_name.as_ref().and_then(|t| qualified_name(&t.names)));
+            c.constraints
+                .iter()
+                .for_each(|n| definition_references(n, references));
+        }
+        Some(NodeEnum::Constraint(c)) => {
+            if let Some(r) = &c.pktable {
+                references.push((schema_or_public(&r.schemaname), r.relname.clone()));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn qualified_name(names: &[Node]) -> Option<(String, String)> {
+    match names
+        .iter()
+        .map(|n| get_sval(&n.node))
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [name] => Some(("public".to_string(), name.clone())),
+        [schema, name] => Some((schema.clone(), name.clone())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Options;
+    use crate::parse::get_nodes;
+
+    #[test]
+    fn table_with_a_foreign_key_and_an_enum_column() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.posts (id int, author_id int, status public.status);
+            CREATE TYPE public.status AS ENUM ('draft', 'published');
+            CREATE TABLE public.authors (id int PRIMARY KEY);
+            CREATE TABLE public.unrelated (id int);
+            ALTER TABLE ONLY public.posts ADD CONSTRAINT posts_author_id_fkey FOREIGN KEY (author_id) REFERENCES public.authors(id);",
+            &Options::default(),
+        );
+        assert_eq!(
+            self_contained_sql(&nodes[0], &nodes),
+            "CREATE TYPE public.status AS ENUM ('draft', 'published');
+
+CREATE TABLE public.authors (id int PRIMARY KEY);
+
+CREATE TABLE public.posts (id int, author_id int, status public.status);
+
+ALTER TABLE ONLY public.posts ADD CONSTRAINT posts_author_id_fkey FOREIGN KEY (author_id) REFERENCES public.authors(id);
+"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use similar::TextDiff;
+
+use crate::verify::{normalize, statements};
+use crate::write::{GENERATED_FILES, is_test_stub};
+
+/// How a generated file differs from the file in the existing tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    Added(PathBuf),
+    Removed(PathBuf),
+    /// The file exists in both, with a unified diff from the existing to the generated content
+    Modified(PathBuf, String),
+}
+
+/// Compare the generated `files` with the tree in `dir`. SQL files only count as modified if
+/// their statements differ after running them through the deparser, so changes in formatting do
+/// not. Test stubs are maintained by hand and are ignored, as is the list of files of `--sync`.
+pub fn diff_tree(files: &BTreeMap<PathBuf, String>, dir: &Path) -> Vec<FileChange> {
+    let existing = existing_files(dir);
+
+    let mut changes = files
+        .iter()
+        .filter(|(path, _)| path.file_name().is_none_or(|n| n != GENERATED_FILES))
+        .filter_map(|(path, content)| match fs::read_to_string(path) {
+            Err(_) => Some(FileChange::Added(path.clone())),
+            Ok(old) if !equivalent(path, &old, content) => {
+                let diff = TextDiff::from_lines(&old, content)
+                    .unified_diff()
+                    .header(&path.display().to_string(), &path.display().to_string())
+                    .to_string();
+                Some(FileChange::Modified(path.clone(), diff))
+            }
+            Ok(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    changes.extend(
+        existing
+            .into_iter()
+            .filter(|path| !files.contains_key(path))
+            .map(FileChange::Removed),
+    );
+
+    changes
+}
+
+/// Whether two versions of a file have the same content, ignoring formatting of SQL files
+fn equivalent(path: &Path, old: &str, new: &str) -> bool {
+    if old == new {
+        return true;
+    }
+    if path.extension().is_none_or(|e| e != "sql") {
+        return false;
+    }
+
+    let normalized = |content: &str| {
+        statements(content)
+            .iter()
+            .map(|sql| normalize(sql))
+            .collect::<Vec<_>>()
+    };
+    normalized(old) == normalized(new)
+}
+
+/// All files in `dir` that a run could have generated
+fn existing_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    entries.flatten().for_each(|entry| {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(existing_files(&path));
+        } else if !is_test_stub(&path) && entry.file_name() != GENERATED_FILES {
+            files.push(path);
+        }
+    });
+    files.sort();
+    files
+}
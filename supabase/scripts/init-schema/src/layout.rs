@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+
+use crate::locations::StatementLocation;
+use crate::options::Options;
+
+/// Decides which file every statement is written to. Implement it to write the tree in your own
+/// layout, e.g. without a directory per category.
+pub trait LayoutStrategy {
+    /// The file of `location` within `base_dir`. `nodes` are all statements of the dump, e.g. to
+    /// find the table that owns a sequence.
+    fn path(
+        &self,
+        location: &StatementLocation,
+        nodes: &[StatementLocation],
+        base_dir: &Path,
+    ) -> PathBuf;
+
+    /// Whether statements that share a file keep the order of the dump instead of being written
+    /// in sections. `pg_dump` orders statements by their dependencies, so this is needed if a
+    /// file contains objects that depend on each other.
+    fn keep_dump_order(&self) -> bool {
+        false
+    }
+}
+
+/// One file per object in a directory per schema and category, e.g. `public/tables/users.sql`
+#[derive(Debug, Default)]
+pub struct DefaultLayout;
+
+impl LayoutStrategy for DefaultLayout {
+    fn path(
+        &self,
+        location: &StatementLocation,
+        nodes: &[StatementLocation],
+        base_dir: &Path,
+    ) -> PathBuf {
+        location.path(base_dir, nodes)
+    }
+}
+
+/// Like `DefaultLayout`, with a directory level named after the first character of every object,
+/// e.g. `public/tables/u/users.sql`
+#[derive(Debug, Default)]
+pub struct BucketByInitialLayout;
+
+impl LayoutStrategy for BucketByInitialLayout {
+    fn path(
+        &self,
+        location: &StatementLocation,
+        nodes: &[StatementLocation],
+        base_dir: &Path,
+    ) -> PathBuf {
+        location.bucketed_path(base_dir, nodes)
+    }
+}
+
+/// A single file per schema with all of its objects, e.g. `public.sql`. Statements that do not
+/// belong to a schema, like casts, are written to the same files as in `DefaultLayout`.
+#[derive(Debug, Default)]
+pub struct FlatLayout;
+
+impl LayoutStrategy for FlatLayout {
+    fn path(
+        &self,
+        location: &StatementLocation,
+        nodes: &[StatementLocation],
+        base_dir: &Path,
+    ) -> PathBuf {
+        let path = location.path(base_dir, nodes);
+        if location.schema().is_none() {
+            return path;
+        }
+
+        // The schema directory of the default layout, which is the schema of the owning object,
+        // e.g. of the table that owns a sequence in another schema
+        let schema = path
+            .strip_prefix(base_dir)
+            .ok()
+            .and_then(|p| p.components().next())
+            .expect("Path of a schema object is not within the base directory");
+        base_dir.join(format!("{}.sql", schema.as_os_str().to_string_lossy()))
+    }
+
+    fn keep_dump_order(&self) -> bool {
+        true
+    }
+}
+
+/// The layout selected by the options
+pub fn layout(options: &Options) -> Box<dyn LayoutStrategy> {
+    if options.flat {
+        Box::new(FlatLayout)
+    } else if options.bucket_by_initial {
+        Box::new(BucketByInitialLayout)
+    } else {
+        Box::new(DefaultLayout)
+    }
+}
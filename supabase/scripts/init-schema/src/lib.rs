@@ -0,0 +1,14 @@
+pub mod config;
+pub mod dependencies;
+pub mod diff;
+pub mod layout;
+pub mod locations;
+pub mod lock;
+pub mod manifest;
+pub mod options;
+pub mod parse;
+pub mod survey;
+pub mod trace;
+pub mod validate;
+pub mod verify;
+pub mod write;
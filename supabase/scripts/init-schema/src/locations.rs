@@ -1,94 +1,207 @@
 use std::{
-    collections::HashSet,
+    collections::BTreeSet,
     path::{Path, PathBuf},
 };
 
-#[derive(Debug)]
+use pg_query::NodeEnum;
+use pg_query::protobuf::{AlterTableType, ObjectType};
+
+use crate::parse::parse_sql;
+
+#[derive(Debug, Clone)]
 pub struct Schema {
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Table {
     pub schema: String,
     pub name: String,
     pub sql: String,
+    /// Schema and name of the tables it inherits from with `INHERITS`, only set on the
+    /// `CREATE TABLE` statement
+    pub inherits: Vec<(String, String)>,
+    /// Schema and name of the sequences created by its identity columns, only set on the
+    /// statement that adds the identity
+    pub identity_sequences: Vec<(String, String)>,
+    /// Names of the constraints added with `ALTER TABLE ... ADD CONSTRAINT`, only set on that
+    /// statement
+    pub constraints: Vec<String>,
 }
 
-#[derive(Debug)]
+/// A table that is a partition of another table
+#[derive(Debug, Clone)]
+pub struct Partition {
+    pub schema: String,
+    pub name: String,
+    pub parent_schema: String,
+    pub parent: String,
+    pub sql: String,
+}
+
+#[derive(Debug, Clone)]
 pub struct Function {
     pub schema: String,
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EnablePolicy {
     pub schema: String,
     pub table: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Policy {
     pub schema: String,
     pub name: String,
     pub table: String,
+    /// Schema and name of the functions called in `USING` and `WITH CHECK`, e.g. `auth.uid`
+    pub functions: Vec<(String, String)>,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Index {
     pub schema: String,
     pub name: String,
     pub table: String,
+    /// Created with `CONCURRENTLY`, which cannot run inside a transaction block
+    pub concurrent: bool,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct View {
     pub schema: String,
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TriggerFunction {
     pub schema: String,
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Trigger {
     pub schema: String,
     pub name: String,
     pub table: String,
+    /// Schema of the trigger function, which may differ from the schema of the table
+    pub function_schema: String,
     pub function: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Enum {
     pub schema: String,
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CompositeType {
     pub schema: String,
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct Collation {
+    pub schema: String,
+    pub name: String,
+    pub sql: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cast {
+    /// Identifies the cast as `<source>__<target>`, which is also its file name
+    pub name: String,
+    pub source: String,
+    pub target: String,
+    pub sql: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextSearch {
+    pub schema: String,
+    pub name: String,
+    /// One of `configuration`, `dictionary`, `template` or `parser`
+    pub kind: String,
+    pub sql: String,
+}
+
+/// An extension, written to the directory of the schema its objects are created in
+#[derive(Debug, Clone)]
+pub struct Extension {
+    pub schema: String,
+    pub name: String,
+    pub sql: String,
+}
+
+#[derive(Debug, Clone)]
 pub struct Setup {
     pub sql: String,
 }
 
-#[derive(Debug)]
+/// A role and its memberships and settings, only written with `--include-roles`
+#[derive(Debug, Clone)]
+pub struct Role {
+    pub name: String,
+    pub sql: String,
+}
+
+/// A server of a foreign data wrapper
+#[derive(Debug, Clone)]
+pub struct ForeignServer {
+    pub name: String,
+    pub sql: String,
+}
+
+/// A user mapping, which is written to the file of its server
+#[derive(Debug, Clone)]
+pub struct UserMapping {
+    pub server: String,
+    /// Name of the role, or e.g. `public` or `current_user`
+    pub user: String,
+    pub sql: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForeignTable {
+    pub schema: String,
+    pub name: String,
+    pub sql: String,
+}
+
+/// An `ALTER DEFAULT PRIVILEGES` statement without `IN SCHEMA`, which applies to all schemas
+#[derive(Debug, Clone)]
+pub struct DefaultPrivileges {
+    pub sql: String,
+}
+
+/// A `DROP` of an object that is not part of the dump
+#[derive(Debug, Clone)]
+pub struct DropStatement {
+    pub sql: String,
+}
+
+/// A `DROP` or rename of an object of the dump, written to the file of the object. Statements
+/// before it refer to the object as it was and statements after it to the new one, so a file with
+/// such a statement keeps the order of the dump.
+#[derive(Debug, Clone)]
+pub struct DumpOrdered {
+    pub object: Box<StatementLocation>,
+}
+
+#[derive(Debug, Clone)]
 pub struct ForeignKey {
     pub constraint_name: String,
     pub source_schema: String,
@@ -98,29 +211,45 @@ pub struct ForeignKey {
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Aggregate {
     pub schema: String,
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Operator {
     pub schema: String,
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Sequence {
     pub table: Option<String>,
+    /// Schema of the owning table, which may differ from the schema of the sequence
+    pub table_schema: Option<String>,
     pub schema: String,
     pub name: String,
     pub sql: String,
 }
 
-#[derive(Debug)]
+/// What a statement does to the object it is routed to. Statements that end up in the same file
+/// are written in sections in this order, so that e.g. the grants of a table always follow its
+/// constraints no matter where they appear in the dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StatementKind {
+    Definition,
+    Alter,
+    Constraint,
+    RowSecurity,
+    Policy,
+    Grant,
+    Comment,
+}
+
+#[derive(Debug, Clone)]
 pub enum StatementLocation {
     Schema(Schema),
     Table(Table),
@@ -138,9 +267,186 @@ pub enum StatementLocation {
     Aggregate(Aggregate),
     Operator(Operator),
     Sequence(Sequence),
+    Cast(Cast),
+    Collation(Collation),
+    TextSearch(TextSearch),
+    Partition(Partition),
+    Drop(DropStatement),
+    ForeignServer(ForeignServer),
+    UserMapping(UserMapping),
+    ForeignTable(ForeignTable),
+    DefaultPrivileges(DefaultPrivileges),
+    Role(Role),
+    Extension(Extension),
+    DumpOrdered(DumpOrdered),
+}
+
+/// The directory each category of statements is written to, relative to its schema. Categories
+/// that share a file are listed in the order they have to be created in, e.g. a table before the
+/// sequences it owns.
+const CATEGORY_DIRECTORIES: &[(&str, &str)] = &[
+    ("Schema", ""),
+    ("Table", "tables"),
+    ("Function", "functions"),
+    ("EnablePolicy", "policies"),
+    ("Policy", "policies"),
+    ("Index", "indices"),
+    ("View", "views"),
+    ("TriggerFunction", "triggers"),
+    ("Trigger", "triggers"),
+    ("EnumNode", "enums"),
+    ("CompositeType", "types"),
+    ("ForeignKey", "fkeys"),
+    ("Setup", ""),
+    ("Aggregate", "aggregates"),
+    ("Operator", "operators"),
+    ("Sequence", "tables"),
+    ("Cast", "casts"),
+    ("Collation", "collations"),
+    ("TextSearch", "text_search"),
+    ("Partition", "tables"),
+    ("Drop", "drops"),
+    ("ForeignServer", "foreign_servers"),
+    ("UserMapping", "foreign_servers"),
+    ("ForeignTable", "foreign_tables"),
+    ("DefaultPrivileges", ""),
+    ("Role", "roles"),
+    ("Extension", "extensions"),
+];
+
+/// Returns the mapping from statement category to the directory `path()` writes it to, e.g.
+/// `("Table", "tables")`. Schema and setup statements are written to index files at the root
+/// of their schema or the output directory, so their directory is empty.
+pub fn category_directories() -> Vec<(&'static str, &'static str)> {
+    CATEGORY_DIRECTORIES.to_vec()
 }
 
 impl StatementLocation {
+    pub fn category(&self) -> &'static str {
+        match self {
+            StatementLocation::Schema(_) => "Schema",
+            StatementLocation::Table(_) => "Table",
+            StatementLocation::Function(_) => "Function",
+            StatementLocation::EnablePolicy(_) => "EnablePolicy",
+            StatementLocation::Policy(_) => "Policy",
+            StatementLocation::Index(_) => "Index",
+            StatementLocation::View(_) => "View",
+            StatementLocation::TriggerFunction(_) => "TriggerFunction",
+            StatementLocation::Trigger(_) => "Trigger",
+            StatementLocation::EnumNode(_) => "EnumNode",
+            StatementLocation::CompositeType(_) => "CompositeType",
+            StatementLocation::ForeignKey(_) => "ForeignKey",
+            StatementLocation::Setup(_) => "Setup",
+            StatementLocation::Aggregate(_) => "Aggregate",
+            StatementLocation::Operator(_) => "Operator",
+            StatementLocation::Sequence(_) => "Sequence",
+            StatementLocation::Cast(_) => "Cast",
+            StatementLocation::Collation(_) => "Collation",
+            StatementLocation::TextSearch(_) => "TextSearch",
+            StatementLocation::Partition(_) => "Partition",
+            StatementLocation::Drop(_) => "Drop",
+            StatementLocation::ForeignServer(_) => "ForeignServer",
+            StatementLocation::UserMapping(_) => "UserMapping",
+            StatementLocation::ForeignTable(_) => "ForeignTable",
+            StatementLocation::DefaultPrivileges(_) => "DefaultPrivileges",
+            StatementLocation::Role(_) => "Role",
+            StatementLocation::Extension(_) => "Extension",
+            StatementLocation::DumpOrdered(n) => n.object.category(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            StatementLocation::Schema(n) => &n.name,
+            StatementLocation::Table(n) => &n.name,
+            StatementLocation::Function(n) => &n.name,
+            StatementLocation::EnablePolicy(n) => &n.table,
+            StatementLocation::Policy(n) => &n.name,
+            StatementLocation::Index(n) => &n.name,
+            StatementLocation::View(n) => &n.name,
+            StatementLocation::TriggerFunction(n) => &n.name,
+            StatementLocation::Trigger(n) => &n.name,
+            StatementLocation::EnumNode(n) => &n.name,
+            StatementLocation::CompositeType(n) => &n.name,
+            StatementLocation::ForeignKey(n) => &n.constraint_name,
+            StatementLocation::Setup(_) => "",
+            StatementLocation::Aggregate(n) => &n.name,
+            StatementLocation::Operator(n) => &n.name,
+            StatementLocation::Sequence(n) => &n.name,
+            StatementLocation::Cast(n) => &n.name,
+            StatementLocation::Collation(n) => &n.name,
+            StatementLocation::TextSearch(n) => &n.name,
+            StatementLocation::Partition(n) => &n.name,
+            StatementLocation::Drop(_) => "",
+            StatementLocation::ForeignServer(n) => &n.name,
+            StatementLocation::UserMapping(n) => &n.user,
+            StatementLocation::ForeignTable(n) => &n.name,
+            StatementLocation::DefaultPrivileges(_) => "",
+            StatementLocation::Role(n) => &n.name,
+            StatementLocation::Extension(n) => &n.name,
+            StatementLocation::DumpOrdered(n) => n.object.name(),
+        }
+    }
+
+    pub fn schema(&self) -> Option<&str> {
+        match self {
+            StatementLocation::Schema(n) => Some(&n.name),
+            StatementLocation::Table(n) => Some(&n.schema),
+            StatementLocation::Function(n) => Some(&n.schema),
+            StatementLocation::EnablePolicy(n) => Some(&n.schema),
+            StatementLocation::Policy(n) => Some(&n.schema),
+            StatementLocation::Index(n) => Some(&n.schema),
+            StatementLocation::View(n) => Some(&n.schema),
+            StatementLocation::TriggerFunction(n) => Some(&n.schema),
+            StatementLocation::Trigger(n) => Some(&n.schema),
+            StatementLocation::EnumNode(n) => Some(&n.schema),
+            StatementLocation::CompositeType(n) => Some(&n.schema),
+            StatementLocation::ForeignKey(n) => Some(&n.source_schema),
+            StatementLocation::Setup(_) => None,
+            StatementLocation::Aggregate(n) => Some(&n.schema),
+            StatementLocation::Operator(n) => Some(&n.schema),
+            StatementLocation::Sequence(n) => Some(&n.schema),
+            StatementLocation::Cast(_) => None,
+            StatementLocation::Collation(n) => Some(&n.schema),
+            StatementLocation::TextSearch(n) => Some(&n.schema),
+            StatementLocation::Partition(n) => Some(&n.schema),
+            StatementLocation::Drop(_) => None,
+            StatementLocation::ForeignServer(_) => None,
+            StatementLocation::UserMapping(_) => None,
+            StatementLocation::ForeignTable(n) => Some(&n.schema),
+            StatementLocation::DefaultPrivileges(_) => None,
+            StatementLocation::Role(_) => None,
+            StatementLocation::Extension(n) => Some(&n.schema),
+            StatementLocation::DumpOrdered(n) => n.object.schema(),
+        }
+    }
+
+    /// Key to order statements that are written to the same file. The statement kind comes
+    /// first so that e.g. a sequence is created before a column default of its table uses it.
+    /// Setup statements are order-sensitive, so they all share the same key.
+    pub fn sort_key(&self) -> (StatementKind, usize, &str) {
+        if let StatementLocation::Setup(_) = self {
+            return (StatementKind::Definition, 0, "");
+        }
+
+        let category = self.category();
+        let rank = CATEGORY_DIRECTORIES
+            .iter()
+            .position(|(c, _)| *c == category)
+            .unwrap_or_else(|| panic!("No directory for category {}", category));
+
+        (self.kind(), rank, self.name())
+    }
+
+    fn directory(&self) -> &'static str {
+        let category = self.category();
+        CATEGORY_DIRECTORIES
+            .iter()
+            .find(|(c, _)| *c == category)
+            .map(|(_, d)| *d)
+            .unwrap_or_else(|| panic!("No directory for category {}", category))
+    }
+
     pub fn sql(&self) -> String {
         ensure_semicolon(match self {
             StatementLocation::Setup(n) => &n.sql,
@@ -159,105 +465,259 @@ impl StatementLocation {
             StatementLocation::Aggregate(n) => &n.sql,
             StatementLocation::Operator(n) => &n.sql,
             StatementLocation::Sequence(n) => &n.sql,
+            StatementLocation::Cast(n) => &n.sql,
+            StatementLocation::Collation(n) => &n.sql,
+            StatementLocation::TextSearch(n) => &n.sql,
+            StatementLocation::Partition(n) => &n.sql,
+            StatementLocation::Drop(n) => &n.sql,
+            StatementLocation::ForeignServer(n) => &n.sql,
+            StatementLocation::UserMapping(n) => &n.sql,
+            StatementLocation::ForeignTable(n) => &n.sql,
+            StatementLocation::DefaultPrivileges(n) => &n.sql,
+            StatementLocation::Role(n) => &n.sql,
+            StatementLocation::Extension(n) => &n.sql,
+            StatementLocation::DumpOrdered(n) => return n.object.sql(),
         })
     }
 
+    /// Another statement for the same object, written to the same file, e.g. a `DROP` of it
+    pub fn with_sql(&self, sql: &str) -> StatementLocation {
+        let mut node = self.clone();
+        let node_sql = match &mut node {
+            StatementLocation::Setup(n) => &mut n.sql,
+            StatementLocation::Schema(n) => &mut n.sql,
+            StatementLocation::Table(n) => &mut n.sql,
+            StatementLocation::Function(n) => &mut n.sql,
+            StatementLocation::EnablePolicy(n) => &mut n.sql,
+            StatementLocation::Policy(n) => &mut n.sql,
+            StatementLocation::Index(n) => &mut n.sql,
+            StatementLocation::View(n) => &mut n.sql,
+            StatementLocation::TriggerFunction(n) => &mut n.sql,
+            StatementLocation::Trigger(n) => &mut n.sql,
+            StatementLocation::EnumNode(n) => &mut n.sql,
+            StatementLocation::CompositeType(n) => &mut n.sql,
+            StatementLocation::ForeignKey(n) => &mut n.sql,
+            StatementLocation::Aggregate(n) => &mut n.sql,
+            StatementLocation::Operator(n) => &mut n.sql,
+            StatementLocation::Sequence(n) => &mut n.sql,
+            StatementLocation::Cast(n) => &mut n.sql,
+            StatementLocation::Collation(n) => &mut n.sql,
+            StatementLocation::TextSearch(n) => &mut n.sql,
+            StatementLocation::Partition(n) => &mut n.sql,
+            StatementLocation::Drop(n) => &mut n.sql,
+            StatementLocation::ForeignServer(n) => &mut n.sql,
+            StatementLocation::UserMapping(n) => &mut n.sql,
+            StatementLocation::ForeignTable(n) => &mut n.sql,
+            StatementLocation::DefaultPrivileges(n) => &mut n.sql,
+            StatementLocation::Role(n) => &mut n.sql,
+            StatementLocation::Extension(n) => &mut n.sql,
+            StatementLocation::DumpOrdered(n) => return n.object.with_sql(sql),
+        };
+        *node_sql = sql.to_string();
+        node
+    }
+
+    /// A `DROP` or rename of this object, see `DumpOrdered`
+    pub fn in_dump_order(&self, sql: &str) -> StatementLocation {
+        StatementLocation::DumpOrdered(DumpOrdered {
+            object: Box::new(self.object().with_sql(sql)),
+        })
+    }
+
+    /// The object the statement is routed to, which is the statement itself unless it is
+    /// `DumpOrdered`
+    pub fn object(&self) -> &StatementLocation {
+        match self {
+            StatementLocation::DumpOrdered(n) => n.object.object(),
+            _ => self,
+        }
+    }
+
+    /// Whether the file of the statement keeps the order of the dump, see `DumpOrdered`
+    pub fn keeps_dump_order(&self) -> bool {
+        matches!(self, StatementLocation::DumpOrdered(_))
+    }
+
+    pub fn kind(&self) -> StatementKind {
+        match self.object() {
+            StatementLocation::EnablePolicy(_) => StatementKind::RowSecurity,
+            StatementLocation::Policy(_) => StatementKind::Policy,
+            _ => match parse_sql(&self.sql()) {
+                NodeEnum::GrantStmt(_) | NodeEnum::AlterDefaultPrivilegesStmt(_) => {
+                    StatementKind::Grant
+                }
+                NodeEnum::CommentStmt(_) => StatementKind::Comment,
+                // Attaching the index of a partition needs the constraint that created it
+                NodeEnum::AlterTableStmt(n) if n.objtype() == ObjectType::ObjectIndex => {
+                    StatementKind::Constraint
+                }
+                NodeEnum::AlterTableStmt(n) => {
+                    let is_constraint = n.cmds.iter().all(|cmd| {
+                        matches!(
+                            &cmd.node,
+                            Some(NodeEnum::AlterTableCmd(c))
+                                if c.subtype() == AlterTableType::AtAddConstraint
+                        )
+                    });
+
+                    if is_constraint {
+                        StatementKind::Constraint
+                    } else {
+                        StatementKind::Alter
+                    }
+                }
+                _ => StatementKind::Definition,
+            },
+        }
+    }
+
+    /// Whether the statement grants privileges or configures row level security
+    pub fn is_security(&self) -> bool {
+        matches!(
+            self.kind(),
+            StatementKind::RowSecurity | StatementKind::Policy | StatementKind::Grant
+        )
+    }
+
     pub fn path(&self, base_dir: &Path, nodes: &[StatementLocation]) -> PathBuf {
         match self {
-            StatementLocation::Schema(n) => base_dir.join(&n.name).join("index.sql"),
+            StatementLocation::Schema(n) => base_dir.join(file_name(&n.name)).join("index.sql"),
             StatementLocation::Setup(_) => base_dir.join("index.sql"),
-            StatementLocation::Table(n) => base_dir
-                .join(&n.schema)
-                .join("tables")
-                .join(format!("{}.sql", n.name)),
+            StatementLocation::Drop(_) => base_dir.join(self.directory()).join("index.sql"),
+            StatementLocation::DefaultPrivileges(_) => base_dir.join("default_privileges.sql"),
+            StatementLocation::DumpOrdered(n) => n.object.path(base_dir, nodes),
+            StatementLocation::Table(n) => match find_partition(nodes, &n.schema, &n.name) {
+                Some(partition) => partition_path(base_dir, partition),
+                None => base_dir
+                    .join(file_name(&n.schema))
+                    .join(self.directory())
+                    .join(format!("{}.sql", file_name(&n.name))),
+            },
+            StatementLocation::Partition(n) => partition_path(base_dir, n),
             StatementLocation::Function(n) => base_dir
-                .join(&n.schema)
-                .join("functions")
-                .join(format!("{}.sql", n.name)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
             StatementLocation::EnablePolicy(n) => base_dir
-                .join(&n.schema)
-                .join("policies")
-                .join(&n.table)
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(file_name(&n.table))
                 .join("enable_rls.sql"),
             StatementLocation::Policy(n) => base_dir
-                .join(&n.schema)
-                .join("policies")
-                .join(&n.table)
-                .join(format!("{}.sql", n.name)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(file_name(&n.table))
+                .join(format!("{}.sql", file_name(&n.name))),
             StatementLocation::Index(n) => base_dir
-                .join(&n.schema)
-                .join("indices")
-                .join(&n.table)
-                .join(format!("{}.sql", n.name)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(file_name(&n.table))
+                .join(format!("{}.sql", file_name(&n.name))),
             StatementLocation::View(n) => base_dir
-                .join(&n.schema)
-                .join("views")
-                .join(format!("{}.sql", n.name)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
             StatementLocation::TriggerFunction(n) => {
-                // Find tables that use this trigger function
-                let tables: HashSet<_> = nodes
+                // Find tables that use this trigger function. The set is sorted so that the
+                // layout does not depend on the order of the triggers.
+                let tables: BTreeSet<(&str, &str)> = nodes
                     .iter()
-                    .filter_map(|node| {
-                        if let StatementLocation::Trigger(t) = node {
-                            if t.function == n.name {
-                                Some(t.table.clone())
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
+                    .filter_map(|node| match node {
+                        StatementLocation::Trigger(t)
+                            if t.function_schema == n.schema && t.function == n.name =>
+                        {
+                            Some((t.schema.as_str(), t.table.as_str()))
                         }
+                        _ => None,
                     })
                     .collect();
 
-                // If function is used by exactly one table, place it in that table's directory
-                // Otherwise, place it in the general triggers directory
+                // If function is used by exactly one table of its own schema, place it in that
+                // table's directory. Otherwise, place it in the general triggers directory.
                 match tables.iter().collect::<Vec<_>>().as_slice() {
-                    [table] => base_dir
-                        .join(&n.schema)
-                        .join("triggers")
-                        .join(table)
-                        .join(format!("{}.sql", n.name)),
+                    [(schema, table)] if *schema == n.schema => base_dir
+                        .join(file_name(&n.schema))
+                        .join(self.directory())
+                        .join(file_name(table))
+                        .join(format!("{}.sql", file_name(&n.name))),
                     _ => base_dir
-                        .join(&n.schema)
-                        .join("triggers")
-                        .join(format!("{}.sql", n.name)),
+                        .join(file_name(&n.schema))
+                        .join(self.directory())
+                        .join(format!("{}.sql", file_name(&n.name))),
                 }
             }
+            // A function of another schema is in a directory of its schema, so that the triggers
+            // of a table that call functions of the same name in different schemas do not share a
+            // file, and a function name with a `.` can't be mistaken for a schema
+            StatementLocation::Trigger(n) if n.function_schema != n.schema => base_dir
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(file_name(&n.table))
+                .join(file_name(&n.function_schema))
+                .join(format!("{}.sql", file_name(&n.function))),
             StatementLocation::Trigger(n) => base_dir
-                .join(&n.schema)
-                .join("triggers")
-                .join(&n.table)
-                .join(format!("{}.sql", n.function)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(file_name(&n.table))
+                .join(format!("{}.sql", file_name(&n.function))),
             StatementLocation::EnumNode(n) => base_dir
-                .join(&n.schema)
-                .join("enums")
-                .join(format!("{}.sql", n.name)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
             StatementLocation::CompositeType(n) => base_dir
-                .join(&n.schema)
-                .join("types")
-                .join(format!("{}.sql", n.name)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
             StatementLocation::ForeignKey(n) => base_dir
-                .join(&n.source_schema)
-                .join("fkeys")
-                .join(&n.source_table)
-                .join(format!("{}.sql", n.constraint_name)),
+                .join(file_name(&n.source_schema))
+                .join(self.directory())
+                .join(file_name(&n.source_table))
+                .join(format!("{}.sql", file_name(&n.constraint_name))),
             StatementLocation::Aggregate(n) => base_dir
-                .join(&n.schema)
-                .join("aggregates")
-                .join(format!("{}.sql", n.name)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
             StatementLocation::Operator(n) => base_dir
-                .join(&n.schema)
-                .join("operators")
-                .join(format!("{}.sql", n.name)),
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", operator_file_name(&n.name))),
+            StatementLocation::Collation(n) => base_dir
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
+            StatementLocation::TextSearch(n) => base_dir
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(&n.kind)
+                .join(format!("{}.sql", file_name(&n.name))),
+            StatementLocation::Cast(n) => base_dir
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
+            StatementLocation::ForeignServer(n) => base_dir
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
+            StatementLocation::Role(n) => base_dir
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
+            StatementLocation::UserMapping(n) => base_dir
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.server))),
+            StatementLocation::ForeignTable(n) => base_dir
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
+            StatementLocation::Extension(n) => base_dir
+                .join(file_name(&n.schema))
+                .join(self.directory())
+                .join(format!("{}.sql", file_name(&n.name))),
             StatementLocation::Sequence(n) => {
-                let table = n.table.clone().unwrap_or_else(|| {
+                let owner = n.owner().or_else(|| {
                     nodes
                         .iter()
                         .filter_map(|node| {
                             if let StatementLocation::Sequence(t) = node {
-                                if t.name == n.name && t.schema == n.schema && t.table.is_some() {
-                                    Some(t.table.clone().unwrap())
+                                if t.name == n.name && t.schema == n.schema {
+                                    t.owner()
                                 } else {
                                     None
                                 }
@@ -266,15 +726,184 @@ impl StatementLocation {
                             }
                         })
                         .next()
-                        .expect("No table found for sequence")
                 });
 
-                base_dir
-                    .join(&n.schema)
-                    .join("tables")
-                    .join(format!("{}.sql", table))
+                match owner {
+                    Some((table_schema, table)) => base_dir
+                        .join(file_name(table_schema))
+                        .join(self.directory())
+                        .join(format!("{}.sql", file_name(table))),
+                    // A sequence that no table owns or uses has a file of its own
+                    None => base_dir
+                        .join(file_name(&n.schema))
+                        .join("sequences")
+                        .join(format!("{}.sql", file_name(&n.name))),
+                }
+            }
+        }
+    }
+
+    /// Like `path`, but with an extra directory below the category directory that is named after
+    /// the first character of the object, e.g. `tables/u/users.sql`. This keeps directories small
+    /// if there are thousands of objects.
+    pub fn bucketed_path(&self, base_dir: &Path, nodes: &[StatementLocation]) -> PathBuf {
+        if let StatementLocation::DumpOrdered(n) = self {
+            return n.object.bucketed_path(base_dir, nodes);
+        }
+        let path = self.path(base_dir, nodes);
+
+        // Number of components before the category directory
+        let depth = match self {
+            StatementLocation::Schema(_)
+            | StatementLocation::Setup(_)
+            | StatementLocation::Drop(_)
+            | StatementLocation::DefaultPrivileges(_) => {
+                return path;
+            }
+            StatementLocation::Cast(_)
+            | StatementLocation::ForeignServer(_)
+            | StatementLocation::UserMapping(_)
+            | StatementLocation::Role(_) => 1,
+            // Text search objects have a directory of their kind within the category directory
+            StatementLocation::TextSearch(_) => 3,
+            _ => 2,
+        };
+
+        let Ok(relative) = path.strip_prefix(base_dir) else {
+            return path;
+        };
+        let components = relative.components().collect::<Vec<_>>();
+        let Some(initial) = components
+            .get(depth)
+            .and_then(|c| c.as_os_str().to_string_lossy().chars().next())
+        else {
+            return path;
+        };
+
+        let bucket = if initial.is_alphanumeric() {
+            initial.to_lowercase().to_string()
+        } else {
+            "_".to_string()
+        };
+
+        let mut bucketed = base_dir.to_path_buf();
+        components.iter().enumerate().for_each(|(i, c)| {
+            if i == depth {
+                bucketed.push(&bucket);
             }
+            bucketed.push(c);
+        });
+        bucketed
+    }
+}
+
+impl Sequence {
+    /// Schema and name of the table the sequence is written to, if known
+    pub fn owner(&self) -> Option<(&str, &str)> {
+        let table = self.table.as_deref()?;
+        Some((self.table_schema.as_deref().unwrap_or(&self.schema), table))
+    }
+}
+
+/// The category, schema and name of the object a file written by `path()` belongs to, e.g.
+/// `("Table", Some("public"), "users")` for `public/tables/users.sql`. `path` is relative to the
+/// output directory. This is the inverse of `path()`, but the file of a trigger is named after its
+/// function, so triggers are identified as their trigger function, whose file is in a directory of
+/// its schema if that differs from the schema of the table. User mappings share the file of their
+/// foreign server and are identified as that server. Files that do not belong to a single object,
+/// like the setup statements or index files, are `None`.
+///
+/// Names are returned as they appear in the path, so a name that `file_name()` changed, e.g. one
+/// with a `/`, is returned changed, as the original cannot be told apart from a name that already
+/// contained the replacement. Schemas named like a directory of the output root (`security`,
+/// `casts`, `foreign_servers`, `roles` or `drops`) are identified by the files of their objects,
+/// but their `index.sql` is always taken as the schema's, not as a role, server or the drops file
+/// of that name.
+pub fn identify(path: &Path) -> Option<(&'static str, Option<String>, String)> {
+    let components = path
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+
+    // Statement files end in `.sql`, test stubs and directory indexes are not part of an object
+    let file = *components.last()?;
+    let stem = file.strip_suffix(".sql")?;
+    if file.ends_with(".test.sql") || file == "__init__.sql" {
+        return None;
+    }
+
+    // The tree that `--split-security` writes mirrors the regular one, otherwise this is a schema
+    // named `security`
+    if let ["security", rest @ ..] = components.as_slice()
+        && let Some(identified) = identify(&rest.iter().collect::<PathBuf>())
+    {
+        return Some(identified);
+    }
+
+    let (category, schema, name) = match components.as_slice() {
+        [schema, "index.sql"] => ("Schema", Some(*schema), *schema),
+        ["casts", _] => ("Cast", None, stem),
+        ["foreign_servers", _] => ("ForeignServer", None, stem),
+        ["roles", _] => ("Role", None, stem),
+        ["default_privileges.sql"] => ("DefaultPrivileges", None, ""),
+        [_] | ["drops", _] => return None,
+        [schema, "tables", _] => ("Table", Some(*schema), stem),
+        [schema, "tables", _, "partitions", _] => ("Partition", Some(*schema), stem),
+        [schema, "sequences", _] => ("Sequence", Some(*schema), stem),
+        [schema, "functions", _] => ("Function", Some(*schema), stem),
+        [schema, "policies", table, "enable_rls.sql"] => ("EnablePolicy", Some(*schema), *table),
+        [schema, "policies", _, _] => ("Policy", Some(*schema), stem),
+        [schema, "indices", _, _] => ("Index", Some(*schema), stem),
+        [schema, "views", _] => ("View", Some(*schema), stem),
+        [_, "triggers", _, function_schema, _] => ("TriggerFunction", Some(*function_schema), stem),
+        [schema, "triggers", _] | [schema, "triggers", _, _] => {
+            ("TriggerFunction", Some(*schema), stem)
         }
+        [schema, "enums", _] => ("EnumNode", Some(*schema), stem),
+        [schema, "types", _] => ("CompositeType", Some(*schema), stem),
+        [schema, "fkeys", _, _] => ("ForeignKey", Some(*schema), stem),
+        [schema, "aggregates", _] => ("Aggregate", Some(*schema), stem),
+        [schema, "operators", _] => ("Operator", Some(*schema), stem),
+        [schema, "collations", _] => ("Collation", Some(*schema), stem),
+        [schema, "text_search", _, _] => ("TextSearch", Some(*schema), stem),
+        [schema, "foreign_tables", _] => ("ForeignTable", Some(*schema), stem),
+        [schema, "extensions", _] => ("Extension", Some(*schema), stem),
+        _ => return None,
+    };
+
+    Some((category, schema.map(str::to_string), name.to_string()))
+}
+
+/// Find the partition node of a table, if the table is a partition
+fn find_partition<'a>(
+    nodes: &'a [StatementLocation],
+    schema: &str,
+    name: &str,
+) -> Option<&'a Partition> {
+    nodes.iter().find_map(|node| match node {
+        StatementLocation::Partition(p) if p.schema == schema && p.name == name => Some(p),
+        _ => None,
+    })
+}
+
+/// Partitions are written next to their parent table, so the whole partition set lives together
+fn partition_path(base_dir: &Path, partition: &Partition) -> PathBuf {
+    base_dir
+        .join(file_name(&partition.parent_schema))
+        .join("tables")
+        .join(file_name(&partition.parent))
+        .join("partitions")
+        .join(format!("{}.sql", file_name(&partition.name)))
+}
+
+/// A name that can be used as a single component of a path. Quoted identifiers may contain path
+/// separators or be `..`, which must not create directories or leave the output directory.
+fn file_name(name: &str) -> String {
+    let name = name.replace(['/', '\\', '\0'], "_");
+    if name.is_empty() || name.chars().all(|c| c == '.') {
+        format!("_{}", name)
+    } else {
+        name
     }
 }
 
@@ -285,3 +914,366 @@ fn ensure_semicolon(s: &str) -> String {
         format!("{};", s)
     }
 }
+
+/// Operator symbols may contain characters that are not valid in file names, such as `/`, so
+/// every character is spelled out, e.g. `@>` becomes `at_gt`
+fn operator_file_name(symbol: &str) -> String {
+    symbol
+        .chars()
+        .map(|c| match c {
+            '+' => "plus".to_string(),
+            '-' => "minus".to_string(),
+            '*' => "star".to_string(),
+            '/' => "slash".to_string(),
+            '<' => "lt".to_string(),
+            '>' => "gt".to_string(),
+            '=' => "eq".to_string(),
+            '~' => "tilde".to_string(),
+            '!' => "bang".to_string(),
+            '@' => "at".to_string(),
+            '#' => "hash".to_string(),
+            '%' => "percent".to_string(),
+            '^' => "caret".to_string(),
+            '&' => "amp".to_string(),
+            '|' => "pipe".to_string(),
+            '`' => "backtick".to_string(),
+            '?' => "question".to_string(),
+            c => c.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Options;
+    use crate::parse::get_nodes;
+
+    /// The path of every statement relative to the output directory
+    fn paths(sql: &str) -> Vec<PathBuf> {
+        let nodes = get_nodes(sql, &Options::default());
+        nodes
+            .iter()
+            .map(|n| n.path(Path::new(""), &nodes))
+            .collect()
+    }
+
+    #[test]
+    fn trigger_calling_a_function_of_another_schema() {
+        let paths = paths(
+            "CREATE FUNCTION private.set_updated_at() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE FUNCTION public.set_updated_at() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE TABLE public.users (id int);
+            CREATE TRIGGER a BEFORE UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION private.set_updated_at();
+            CREATE TRIGGER b BEFORE UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION public.set_updated_at();",
+        );
+        let function = Path::new("private/triggers/set_updated_at.sql");
+        let trigger = Path::new("public/triggers/users/private/set_updated_at.sql");
+
+        assert_eq!(paths[0], function);
+        assert_eq!(
+            paths[1],
+            Path::new("public/triggers/users/set_updated_at.sql")
+        );
+        assert_eq!(paths[3], trigger);
+        assert_eq!(paths[4], paths[1]);
+
+        let private = Some((
+            "TriggerFunction",
+            Some("private".to_string()),
+            "set_updated_at".to_string(),
+        ));
+        assert_eq!(identify(function), private);
+        assert_eq!(identify(trigger), private);
+    }
+
+    #[test]
+    fn trigger_function_shared_by_several_tables() {
+        let paths = paths(
+            "CREATE FUNCTION public.updated_at() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE FUNCTION public.audit() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE TABLE public.users (id int);
+            CREATE TABLE public.posts (id int);
+            CREATE TABLE public.comments (id int);
+            CREATE TRIGGER updated_at BEFORE UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION public.updated_at();
+            CREATE TRIGGER updated_at BEFORE UPDATE ON public.posts FOR EACH ROW EXECUTE FUNCTION public.updated_at();
+            CREATE TRIGGER updated_at BEFORE UPDATE ON public.comments FOR EACH ROW EXECUTE FUNCTION public.updated_at();
+            CREATE TRIGGER audit_insert AFTER INSERT ON public.posts FOR EACH ROW EXECUTE FUNCTION public.audit();
+            CREATE TRIGGER audit_update AFTER UPDATE ON public.posts FOR EACH ROW EXECUTE FUNCTION public.audit();",
+        );
+        assert_eq!(
+            paths,
+            [
+                "public/triggers/updated_at.sql",
+                "public/triggers/posts/audit.sql",
+                "public/tables/users.sql",
+                "public/tables/posts.sql",
+                "public/tables/comments.sql",
+                "public/triggers/users/updated_at.sql",
+                "public/triggers/posts/updated_at.sql",
+                "public/triggers/comments/updated_at.sql",
+                "public/triggers/posts/audit.sql",
+                "public/triggers/posts/audit.sql",
+            ]
+            .map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn same_named_trigger_functions_of_two_schemas() {
+        let paths = paths(
+            "CREATE FUNCTION api.set_updated_at() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE FUNCTION public.set_updated_at() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE TABLE api.profiles (id int);
+            CREATE TABLE public.users (id int);
+            CREATE TRIGGER a BEFORE UPDATE ON api.profiles FOR EACH ROW EXECUTE FUNCTION api.set_updated_at();
+            CREATE TRIGGER b BEFORE UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION public.set_updated_at();",
+        );
+        assert_eq!(
+            paths[0],
+            Path::new("api/triggers/profiles/set_updated_at.sql")
+        );
+        assert_eq!(
+            paths[1],
+            Path::new("public/triggers/users/set_updated_at.sql")
+        );
+        assert_eq!(paths[4], paths[0]);
+        assert_eq!(paths[5], paths[1]);
+    }
+
+    #[test]
+    fn trigger_function_with_a_dot_in_its_name() {
+        let paths = paths(
+            "CREATE FUNCTION public.\"audit.log\"() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE TABLE public.users (id int);
+            CREATE TRIGGER a AFTER INSERT ON public.users FOR EACH ROW EXECUTE FUNCTION public.\"audit.log\"();",
+        );
+        assert_eq!(paths[2], Path::new("public/triggers/users/audit.log.sql"));
+        assert_eq!(
+            identify(&paths[2]),
+            Some((
+                "TriggerFunction",
+                Some("public".to_string()),
+                "audit.log".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn deferred_constraint_trigger_on_its_own_table() {
+        let sql = "CREATE FUNCTION public.check_order() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE TABLE public.customers (id int);
+            CREATE TABLE public.orders (id int, customer_id int);
+            CREATE CONSTRAINT TRIGGER check_order AFTER INSERT ON public.orders FROM public.customers DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE FUNCTION public.check_order();";
+        let paths = paths(sql);
+        let trigger = Path::new("public/triggers/orders/check_order.sql");
+        assert_eq!(paths[0], trigger);
+        assert_eq!(paths[3], trigger);
+
+        let nodes = get_nodes(sql, &Options::default());
+        assert!(nodes[3].sql().contains("DEFERRABLE INITIALLY DEFERRED"));
+    }
+
+    #[test]
+    fn sequence_without_a_table() {
+        assert_eq!(
+            paths("CREATE SEQUENCE api.counter;"),
+            vec![PathBuf::from("api/sequences/counter.sql")]
+        );
+    }
+
+    #[test]
+    fn user_mapping_in_the_file_of_its_server() {
+        let paths = paths(
+            "CREATE SERVER remote FOREIGN DATA WRAPPER postgres_fdw;
+            CREATE USER MAPPING FOR postgres SERVER remote;",
+        );
+        assert_eq!(paths[1], paths[0]);
+        assert_eq!(
+            identify(&paths[1]),
+            Some(("ForeignServer", None, "remote".to_string()))
+        );
+    }
+
+    #[test]
+    fn changes_of_a_foreign_table_in_its_file() {
+        let paths = paths(
+            "CREATE SERVER remote FOREIGN DATA WRAPPER postgres_fdw;
+            CREATE FOREIGN TABLE api.remote_users (id int) SERVER remote;
+            ALTER FOREIGN TABLE api.remote_users OPTIONS (ADD table_name 'users');
+            ALTER TABLE api.remote_users ALTER COLUMN id OPTIONS (column_name 'user_id');",
+        );
+        let foreign_table = Path::new("api/foreign_tables/remote_users.sql");
+        assert_eq!(paths[1], foreign_table);
+        assert_eq!(paths[2], foreign_table);
+        assert_eq!(paths[3], foreign_table);
+    }
+
+    #[test]
+    fn schemas_named_like_top_level_directories() {
+        let paths = paths(
+            "CREATE SCHEMA casts;
+            CREATE TABLE casts.users (id int);
+            CREATE SCHEMA security;
+            CREATE TABLE security.users (id int);",
+        );
+        let identified = paths.iter().map(|path| identify(path)).collect::<Vec<_>>();
+        assert_eq!(
+            identified,
+            vec![
+                Some(("Schema", Some("casts".to_string()), "casts".to_string())),
+                Some(("Table", Some("casts".to_string()), "users".to_string())),
+                Some((
+                    "Schema",
+                    Some("security".to_string()),
+                    "security".to_string()
+                )),
+                Some(("Table", Some("security".to_string()), "users".to_string())),
+            ]
+        );
+        assert_eq!(
+            identify(Path::new("security/public/tables/users.sql")),
+            Some(("Table", Some("public".to_string()), "users".to_string()))
+        );
+    }
+
+    #[test]
+    fn text_search_bucketed_by_its_name() {
+        let sql =
+            "CREATE TEXT SEARCH CONFIGURATION public.english_simple (COPY = pg_catalog.english);";
+        let nodes = get_nodes(sql, &Options::default());
+        assert_eq!(
+            nodes[0].bucketed_path(Path::new(""), &nodes),
+            Path::new("public/text_search/configuration/e/english_simple.sql")
+        );
+    }
+
+    #[test]
+    fn nondeterministic_collation() {
+        let sql = "CREATE COLLATION public.case_insensitive (provider = icu, locale = 'und-u-ks-level2', deterministic = false);";
+        let nodes = get_nodes(sql, &Options::default());
+        let StatementLocation::Collation(collation) = &nodes[0] else {
+            panic!("Expected a collation, found {}", nodes[0].category());
+        };
+        assert_eq!(
+            (collation.schema.as_str(), collation.name.as_str()),
+            ("public", "case_insensitive")
+        );
+        assert_eq!(
+            paths(sql),
+            vec![PathBuf::from("public/collations/case_insensitive.sql")]
+        );
+    }
+
+    #[test]
+    fn schema_qualified_operator() {
+        let sql = "CREATE OPERATOR api.=== (LEFTARG = int, RIGHTARG = int, FUNCTION = int4eq);";
+        let nodes = get_nodes(sql, &Options::default());
+        let StatementLocation::Operator(operator) = &nodes[0] else {
+            panic!("Expected an operator, found {}", nodes[0].category());
+        };
+        assert_eq!(
+            (operator.schema.as_str(), operator.name.as_str()),
+            ("api", "===")
+        );
+        assert_eq!(
+            paths(sql),
+            vec![PathBuf::from("api/operators/eq_eq_eq.sql")]
+        );
+    }
+
+    /// Number of the variant of a statement. The match has no wildcard, so that a new variant
+    /// fails to compile until it is added here and to `every_category_has_a_directory`.
+    fn variant(n: &StatementLocation) -> usize {
+        match n {
+            StatementLocation::Schema(_) => 0,
+            StatementLocation::Table(_) => 1,
+            StatementLocation::Function(_) => 2,
+            StatementLocation::EnablePolicy(_) => 3,
+            StatementLocation::Policy(_) => 4,
+            StatementLocation::Index(_) => 5,
+            StatementLocation::View(_) => 6,
+            StatementLocation::TriggerFunction(_) => 7,
+            StatementLocation::Trigger(_) => 8,
+            StatementLocation::EnumNode(_) => 9,
+            StatementLocation::CompositeType(_) => 10,
+            StatementLocation::ForeignKey(_) => 11,
+            StatementLocation::Setup(_) => 12,
+            StatementLocation::Aggregate(_) => 13,
+            StatementLocation::Operator(_) => 14,
+            StatementLocation::Sequence(_) => 15,
+            StatementLocation::Cast(_) => 16,
+            StatementLocation::Collation(_) => 17,
+            StatementLocation::TextSearch(_) => 18,
+            StatementLocation::Partition(_) => 19,
+            StatementLocation::Drop(_) => 20,
+            StatementLocation::ForeignServer(_) => 21,
+            StatementLocation::UserMapping(_) => 22,
+            StatementLocation::ForeignTable(_) => 23,
+            StatementLocation::DefaultPrivileges(_) => 24,
+            StatementLocation::Role(_) => 25,
+            StatementLocation::Extension(_) => 26,
+            // Written to the file of the object it wraps, so it has no category of its own
+            StatementLocation::DumpOrdered(_) => 27,
+        }
+    }
+
+    #[test]
+    fn every_category_has_a_directory() {
+        let options = Options {
+            include_roles: true,
+            ..Options::default()
+        };
+        let nodes = get_nodes(
+            "SET statement_timeout = 0;
+            CREATE ROLE app;
+            CREATE SCHEMA api;
+            CREATE EXTENSION pgcrypto WITH SCHEMA api;
+            CREATE TYPE public.mood AS ENUM ('happy');
+            CREATE TYPE public.pair AS (a int, b int);
+            CREATE CAST (text AS public.mood) WITH INOUT;
+            CREATE COLLATION public.c (provider = icu, locale = 'und');
+            CREATE TEXT SEARCH CONFIGURATION public.simple_en (COPY = pg_catalog.english);
+            CREATE SEQUENCE public.counter;
+            CREATE TABLE public.teams (id int PRIMARY KEY);
+            CREATE TABLE public.users (id int, team_id int);
+            CREATE TABLE public.events (id int) PARTITION BY RANGE (id);
+            CREATE TABLE public.events_1 PARTITION OF public.events FOR VALUES FROM (0) TO (10);
+            CREATE FUNCTION public.add(a int, b int) RETURNS int LANGUAGE sql AS $$ SELECT a + b $$;
+            CREATE AGGREGATE public.total(int) (SFUNC = int4pl, STYPE = int);
+            CREATE OPERATOR public.=== (LEFTARG = int, RIGHTARG = int, FUNCTION = int4eq);
+            CREATE VIEW public.v AS SELECT 1 AS one;
+            CREATE INDEX users_team_id ON public.users (team_id);
+            ALTER TABLE ONLY public.users ADD CONSTRAINT users_team_id_fkey FOREIGN KEY (team_id) REFERENCES public.teams(id);
+            CREATE FUNCTION public.touch() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE TRIGGER touch BEFORE UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION public.touch();
+            ALTER TABLE public.users ENABLE ROW LEVEL SECURITY;
+            CREATE POLICY read ON public.users FOR SELECT USING (true);
+            CREATE SERVER remote FOREIGN DATA WRAPPER postgres_fdw;
+            CREATE USER MAPPING FOR postgres SERVER remote;
+            CREATE FOREIGN TABLE public.remote_users (id int) SERVER remote;
+            ALTER DEFAULT PRIVILEGES GRANT SELECT ON TABLES TO anon;
+            DROP VIEW public.v;
+            DROP TABLE public.gone;",
+            &options,
+        );
+
+        let variants = nodes.iter().map(variant).collect::<BTreeSet<_>>();
+        assert_eq!(variants, (0..28).collect::<BTreeSet<_>>());
+
+        // Every category but that of `DumpOrdered`, which takes the one of its object
+        assert_eq!(category_directories().len(), 27);
+        nodes.iter().for_each(|n| {
+            assert!(
+                category_directories()
+                    .iter()
+                    .any(|(category, _)| *category == n.category()),
+                "{}",
+                n.category()
+            );
+            n.sort_key();
+            n.path(Path::new(""), &nodes);
+        });
+    }
+}
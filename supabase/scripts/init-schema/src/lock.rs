@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use crate::locations::StatementLocation;
+use crate::parse::deparse_sql;
+
+/// Signatures of all objects of a run. A signature only changes if the parsed statements of the
+/// object change, so reformatting the dump does not show up in the lock.
+#[derive(Debug, Default)]
+pub struct Lock {
+    /// Normalized statements of every object, keyed by kind and qualified name
+    objects: BTreeMap<String, Vec<String>>,
+}
+
+impl Lock {
+    /// Add the statement of a node to the signature of its object
+    pub fn record(&mut self, node: &StatementLocation) {
+        if matches!(node, StatementLocation::Setup(_)) {
+            return;
+        }
+
+        let name = match node.schema() {
+            Some(schema) => format!("{}.{}", schema, node.name()),
+            None => node.name().to_string(),
+        };
+
+        self.objects
+            .entry(format!("{} {}", node.category(), name))
+            .or_default()
+            .push(deparse_sql(&node.sql()));
+    }
+
+    /// One line per object with its kind, name and signature, sorted by kind and name
+    pub fn to_lock(&self) -> String {
+        self.objects
+            .iter()
+            .map(|(object, statements)| {
+                format!("{} {:016x}\n", object, fnv1a(&statements.join("\n")))
+            })
+            .collect()
+    }
+}
+
+/// 64-bit FNV-1a hash. Unlike the hasher of the standard library, it is guaranteed to be stable
+/// across Rust versions, so lock files can be compared between machines.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -1,14 +1,22 @@
-use parse::get_nodes;
+use init_schema::config::{self, Config, Layout};
+use init_schema::diff::{FileChange, diff_tree};
+use init_schema::layout::layout;
+use init_schema::options::{Options, QuoteStyle, Verbosity};
+use init_schema::parse::{get_nodes_traced, get_nodes_with_errors};
+use init_schema::survey::survey;
+use init_schema::trace::Tracer;
+use init_schema::validate::{path_collisions, public_grants, validate};
+use init_schema::verify::missing_statements;
+use init_schema::write::{MemorySink, is_test_stub, write_nodes, write_nodes_to};
+use std::collections::BTreeSet;
 use std::env;
 use std::fs;
-use std::io::Read;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
 use std::process::{Command, Stdio};
-use write::write_nodes;
 
-mod locations;
-mod parse;
-mod write;
+use clap::Parser;
+use log::{LevelFilter, error, info, warn};
 
 fn find_supabase_dir() -> PathBuf {
     let mut current_dir = env::current_dir().expect("Failed to get current directory");
@@ -31,50 +39,327 @@ fn find_supabase_dir() -> PathBuf {
     }
 }
 
-fn main() {
-    // Find the Supabase root directory
-    let supabase_dir = find_supabase_dir();
-    println!("Found Supabase directory at: {}", supabase_dir.display());
+fn init_logger(verbosity: Verbosity) {
+    let level = match verbosity {
+        Verbosity::Quiet => LevelFilter::Error,
+        Verbosity::Normal => LevelFilter::Info,
+        Verbosity::Verbose => LevelFilter::Debug,
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format_timestamp(None)
+        .format_target(false)
+        .parse_default_env()
+        .init();
+}
+
+/// A `supabase` CLI command in the Supabase directory. Its output is hidden unless the phases of
+/// the run are logged, errors are always shown.
+fn supabase(args: &[&str], supabase_dir: &Path) -> Command {
+    let mut command = Command::new("supabase");
+    command.args(args).current_dir(supabase_dir);
+
+    if !log::log_enabled!(log::Level::Info) {
+        command.stdout(Stdio::null());
+    }
+
+    command
+}
+
+/// Split the schema of the local Supabase database into one file per object, ready to be used
+/// as a declarative schema
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The Supabase directory that contains config.toml. Defaults to the closest directory named
+    /// `supabase` above the current directory.
+    #[arg(long)]
+    supabase_dir: Option<PathBuf>,
+
+    /// Only write objects of these schemas, e.g. `public,api`. All schemas are written by default.
+    #[arg(long, value_delimiter = ',')]
+    schemas: Vec<String>,
+
+    /// Directory to write the files to. Relative paths are resolved against the Supabase
+    /// directory. Defaults to `schemas`.
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Read the dump from these files instead of resetting and dumping the local database. The
+    /// files are concatenated in the order given.
+    #[arg(long, num_args = 1..)]
+    input: Vec<PathBuf>,
+
+    /// Dump the current state of the local database instead of resetting it first
+    #[arg(long, conflicts_with = "input")]
+    skip_reset: bool,
+
+    /// Assume that Supabase is running instead of checking its status and starting it
+    #[arg(long, conflicts_with = "input")]
+    skip_start: bool,
+
+    /// Log the files that would be written without touching the output directory
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Compare the files that would be written with the existing output directory and print the
+    /// differences without writing anything. Exits with an error if there are any.
+    #[arg(long, conflicts_with = "dry_run")]
+    diff: bool,
+
+    /// Check that every statement was written by reading the generated files back
+    #[arg(long)]
+    verify: bool,
+
+    /// Pipe every generated file through this shell command, e.g. `pg_format -`, and replace it
+    /// with the output
+    #[arg(long)]
+    format_cmd: Option<String>,
+
+    /// Abort if the formatter fails on a file instead of keeping the file as it is
+    #[arg(long)]
+    strict: bool,
+
+    /// Keep the existing output directory instead of deleting it before writing
+    #[arg(long)]
+    no_clean: bool,
+
+    /// Warn about unsupported statements and leave them out instead of aborting
+    #[arg(long)]
+    skip_unsupported: bool,
+
+    /// Continue past statements that fail or are unsupported and list all of them at the end
+    /// instead of aborting at the first one. Nothing is written if there are any.
+    #[arg(long, conflicts_with = "skip_unsupported")]
+    report_all: bool,
+
+    /// Print how often every kind of statement occurs in the dump and whether it is supported,
+    /// with an example of each, instead of writing files
+    #[arg(long)]
+    survey: bool,
+
+    /// Write grants, policies and RLS enablement into a separate `security/` tree
+    #[arg(long)]
+    split_security: bool,
+
+    /// Rewrite `EXECUTE PROCEDURE` in trigger definitions to `EXECUTE FUNCTION`
+    #[arg(long)]
+    normalize_trigger_exec: bool,
+
+    /// Format every statement consistently by running it through the Postgres deparser
+    #[arg(long)]
+    deparse: bool,
+
+    /// Write a JSON index of all generated files to this path, relative to the output directory
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Write the signature of every object to this path, relative to the output directory
+    #[arg(long)]
+    lock: Option<PathBuf>,
+
+    /// Warn about references to objects that are missing from the dump
+    #[arg(long)]
+    validate: bool,
+
+    /// Warn about grants to the PUBLIC pseudo-role
+    #[arg(long)]
+    flag_public_grants: bool,
+
+    /// How identifiers in reconstructed statements are quoted
+    #[arg(long, value_enum, default_value_t)]
+    quote_style: QuoteStyle,
+
+    /// Keep the existing output and only write files for objects that do not have one yet
+    #[arg(long)]
+    append_new_only: bool,
+
+    /// Rewrite the existing output in place and remove files of objects that no longer exist
+    #[arg(long)]
+    sync: bool,
+
+    /// Write the whole tree into this subdirectory of the output directory
+    #[arg(long, value_parser = parse_prefix)]
+    prefix: Option<PathBuf>,
+
+    /// Print how every statement was routed to stderr
+    #[arg(long)]
+    trace: bool,
 
-    let status = Command::new("supabase")
-        .args(["status"])
-        .current_dir(&supabase_dir)
-        .status()
-        .expect("Failed to reset database");
+    /// Write an `__init__.sql` into every directory that includes its statement files, sorted by
+    /// name, except that a table comes after the tables it inherits from
+    #[arg(long)]
+    dir_index: bool,
+
+    /// Write a `_order.sql` into every schema directory that includes its files in the order
+    /// they can be applied in
+    #[arg(long)]
+    schema_order: bool,
+
+    /// Keep the owners of tables, views and sequences instead of skipping them
+    #[arg(long)]
+    keep_owners: bool,
+
+    /// Write a pgTAP test stub next to every function and table with policies, unless it exists
+    #[arg(long)]
+    emit_test_stubs: bool,
+
+    /// Add a directory level named after the first character of every object, e.g.
+    /// `tables/u/users.sql`
+    #[arg(long)]
+    bucket_by_initial: bool,
+
+    /// Write a single `<schema>.sql` file per schema with all of its objects
+    #[arg(long, conflicts_with = "bucket_by_initial")]
+    flat: bool,
+
+    /// Leave out `CREATE SCHEMA` statements, for databases where the schemas already exist
+    #[arg(long)]
+    no_schema_create: bool,
+
+    /// Also strip these psql meta-commands from the dump, e.g. `set,pset`. `\connect`, `\c`,
+    /// `\restrict` and `\unrestrict` are always stripped.
+    #[arg(long, value_delimiter = ',')]
+    strip_meta_commands: Vec<String>,
+
+    /// Leave out `SET` statements of these variables, e.g.
+    /// `statement_timeout,lock_timeout,idle_in_transaction_session_timeout`
+    #[arg(long, value_delimiter = ',')]
+    skip_settings: Vec<String>,
+
+    /// Write role definitions and memberships to `roles/<name>.sql` instead of skipping them
+    #[arg(long)]
+    include_roles: bool,
+
+    /// Start every file with the `SET` statements of the dump, so that it can be run on its own
+    #[arg(long)]
+    set_header: bool,
+
+    /// Move foreign keys defined inside `CREATE TABLE` into `ALTER TABLE` statements in `fkeys/`
+    #[arg(long)]
+    extract_inline_fkeys: bool,
+
+    /// Fail on comments and grants of objects in schemas that are not part of the dump instead of
+    /// skipping them with a warning
+    #[arg(long)]
+    strict_schemas: bool,
+
+    /// Write comments after the other statements of every file, also with `--flat`
+    #[arg(long)]
+    comments_last: bool,
+
+    /// Also log the file and kind of every statement
+    #[arg(long, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Only log errors
+    #[arg(long)]
+    quiet: bool,
+}
+
+impl Cli {
+    fn verbosity(&self) -> Verbosity {
+        if self.verbose {
+            Verbosity::Verbose
+        } else if self.quiet {
+            Verbosity::Quiet
+        } else {
+            Verbosity::Normal
+        }
+    }
+
+    /// Take the options of the configuration file that are not set on the command line
+    fn apply(&mut self, config: Config) {
+        if self.schemas.is_empty() {
+            self.schemas = config.schemas.unwrap_or_default();
+        }
+        self.output_dir = self.output_dir.take().or(config.output_dir);
+        self.skip_unsupported |= config.skip_unsupported.unwrap_or_default() && !self.report_all;
+        if !self.flat && !self.bucket_by_initial {
+            self.flat = config.layout == Some(Layout::Flat);
+            self.bucket_by_initial = config.layout == Some(Layout::BucketByInitial);
+        }
+        self.format_cmd = self.format_cmd.take().or(config.format_cmd);
+        if self.skip_settings.is_empty() {
+            self.skip_settings = config.skip_settings.unwrap_or_default();
+        }
+    }
+
+    fn options(&self) -> Options {
+        Options {
+            split_security: self.split_security,
+            normalize_trigger_exec: self.normalize_trigger_exec,
+            deparse: self.deparse,
+            manifest: self.manifest.clone(),
+            validate: self.validate,
+            quote_style: self.quote_style,
+            append_new_only: self.append_new_only,
+            trace: self.trace,
+            dir_index: self.dir_index,
+            schema_order: self.schema_order,
+            keep_owners: self.keep_owners,
+            verbosity: self.verbosity(),
+            flag_public_grants: self.flag_public_grants,
+            sync: self.sync,
+            lock: self.lock.clone(),
+            prefix: self.prefix.clone(),
+            skip_unsupported: self.skip_unsupported,
+            schemas: self.schemas.clone(),
+            emit_test_stubs: self.emit_test_stubs,
+            bucket_by_initial: self.bucket_by_initial,
+            flat: self.flat,
+            skip_schema_create: self.no_schema_create,
+            meta_commands: self.strip_meta_commands.clone(),
+            skip_settings: self.skip_settings.clone(),
+            include_roles: self.include_roles,
+            set_header: self.set_header,
+            extract_inline_fkeys: self.extract_inline_fkeys,
+            strict_schemas: self.strict_schemas,
+            comments_last: self.comments_last,
+        }
+    }
+}
+
+/// Reset the local database without seeding it and dump its schema. Starting and resetting can
+/// be skipped, e.g. if the schema did not change since the last run. Returns `None` if one of
+/// the `supabase` commands fails.
+fn dump_schema(supabase_dir: &Path, skip_start: bool, skip_reset: bool) -> Option<String> {
+    let is_running = skip_start
+        || supabase(&["status"], supabase_dir)
+            .status()
+            .expect("Failed to reset database")
+            .success();
 
     // For some reason, there is no start --no-seed so we have to start first and then reset...
-    if !status.success() {
-        println!("Supabase is not running. Starting Supabase...");
-        let status = Command::new("supabase")
-            .args(["start"])
-            .current_dir(&supabase_dir)
+    if !is_running {
+        info!("Supabase is not running. Starting Supabase...");
+        let status = supabase(&["start"], supabase_dir)
             .status()
             .expect("Failed to reset database");
 
         if !status.success() {
-            eprintln!("Failed to start Supabase");
-            return;
+            error!("Failed to start Supabase");
+            return None;
         }
     }
 
     // Reset the database without seeding
-    println!("Resetting Supabase database without seeding...");
-    let reset_status = Command::new("supabase")
-        .args(["db", "reset", "--no-seed"])
-        .current_dir(&supabase_dir)
-        .status()
-        .expect("Failed to reset database");
-
-    if !reset_status.success() {
-        eprintln!("Database reset failed");
-        return;
+    if !skip_reset {
+        info!("Resetting Supabase database without seeding...");
+        let reset_status = supabase(&["db", "reset", "--no-seed"], supabase_dir)
+            .status()
+            .expect("Failed to reset database");
+
+        if !reset_status.success() {
+            error!("Database reset failed");
+            return None;
+        }
     }
 
     // Dump the schema directly to memory
-    println!("Dumping schema...");
-    let dump_output = Command::new("supabase")
-        .args(["db", "dump", "--local"])
-        .current_dir(&supabase_dir)
+    info!("Dumping schema...");
+    let dump_output = supabase(&["db", "dump", "--local"], supabase_dir)
         .stdout(Stdio::piped())
         .spawn()
         .expect("Failed to start schema dump")
@@ -88,16 +373,355 @@ fn main() {
         .read_to_string(&mut schema)
         .expect("Failed to read schema dump output");
 
+    Some(schema)
+}
+
+/// Pipe a file through a formatter command run by the shell and replace it with the output. The
+/// file is left as it is if the command fails.
+fn format_file(path: &Path, command: &str) -> Result<(), String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Written from another thread, so that a formatter that streams its output can't block on a
+    // full pipe
+    let mut stdin = child.stdin.take().expect("Failed to open formatter stdin");
+    let writer = std::thread::spawn(move || stdin.write_all(content.as_bytes()));
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    writer
+        .join()
+        .expect("Failed to write to formatter")
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    fs::write(path, output.stdout).map_err(|e| e.to_string())
+}
+
+/// A prefix is a single directory name, as the directory it names is deleted on every run and
+/// must stay within the output directory
+fn parse_prefix(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    match path.components().collect::<Vec<_>>().as_slice() {
+        [Component::Normal(_)] => Ok(path),
+        _ => Err(format!("'{}' is not a single directory name", value)),
+    }
+}
+
+/// Directory next to `dir` that a run writes to before it replaces `dir`. Being on the same file
+/// system, it can be renamed.
+fn staging_dir(dir: &Path) -> PathBuf {
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    dir.with_file_name(format!(".{}.staging", name))
+}
+
+/// Replace `dir` with `new`. The previous tree is moved aside first and only deleted once `new` is
+/// in place, so that `dir` is never missing its files, and it is moved back if that fails.
+fn replace_dir(new: &Path, dir: &Path) -> std::io::Result<()> {
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let previous = dir.with_file_name(format!(".{}.previous", name));
+
+    // Left behind by a run that failed to delete it
+    let _ = fs::remove_dir_all(&previous);
+    let moved_aside = match fs::rename(dir, &previous) {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => return Err(e),
+    };
+
+    if let Err(e) = fs::rename(new, dir) {
+        if moved_aside && let Err(restore) = fs::rename(&previous, dir) {
+            error!(
+                "Failed to restore {} from {}: {}",
+                dir.display(),
+                previous.display(),
+                restore
+            );
+        }
+        return Err(e);
+    }
+
+    if moved_aside && let Err(e) = fs::remove_dir_all(&previous) {
+        warn!(
+            "Failed to remove the previous output {}: {}",
+            previous.display(),
+            e
+        );
+    }
+    Ok(())
+}
+
+/// The directory within the staging directory that replaces the output, which is the prefix if
+/// there is one
+fn staged(staging: &Path, options: &Options) -> PathBuf {
+    match &options.prefix {
+        Some(prefix) => staging.join(prefix),
+        None => staging.to_path_buf(),
+    }
+}
+
+/// Copy the test stubs of a previous run into the staging directory. Stubs are only written once
+/// and then maintained by hand, so they must survive the replacement.
+fn copy_test_stubs(from: &Path, to: &Path) {
+    let Ok(entries) = fs::read_dir(from) else {
+        return;
+    };
+    entries.flatten().for_each(|entry| {
+        let path = entry.path();
+        if path.is_dir() {
+            copy_test_stubs(&path, &to.join(entry.file_name()));
+        } else if is_test_stub(&path) {
+            fs::create_dir_all(to).expect("Failed to create staging directory");
+            fs::copy(&path, to.join(entry.file_name())).expect("Failed to copy test stub");
+        }
+    });
+}
+
+fn main() {
+    let mut cli = Cli::parse();
+    init_logger(cli.verbosity());
+
+    // Find the Supabase root directory
+    let supabase_dir = cli.supabase_dir.clone().unwrap_or_else(find_supabase_dir);
+    info!("Found Supabase directory at: {}", supabase_dir.display());
+
+    if let Some(config) = config::load(&supabase_dir) {
+        info!(
+            "Using options of {}",
+            supabase_dir.join(config::CONFIG_FILE).display()
+        );
+        cli.apply(config);
+    }
+    let options = cli.options();
+
+    let schema = if cli.input.is_empty() {
+        match dump_schema(&supabase_dir, cli.skip_start, cli.skip_reset) {
+            Some(schema) => schema,
+            None => return,
+        }
+    } else {
+        // Statements may refer to objects of earlier files, so the order is kept
+        cli.input
+            .iter()
+            .map(|input| {
+                info!("Reading schema from {}...", input.display());
+                fs::read_to_string(input).expect("Failed to read input file")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    if cli.survey {
+        println!("{:>9} {:>9}  kind", "unhandled", "handled");
+        survey(&schema, &options).iter().for_each(|count| {
+            println!(
+                "{:>9} {:>9}  {}",
+                count.unhandled, count.handled, count.kind
+            );
+            println!(
+                "{:>21}e.g. {}",
+                "",
+                count
+                    .example
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        });
+        return;
+    }
+
     // Process the schema
-    println!("Processing schema...");
-    let nodes = get_nodes(&schema);
+    info!("Processing schema...");
+    let mut tracer = if options.trace {
+        Tracer::stderr()
+    } else {
+        Tracer::default()
+    };
+    let nodes = if cli.report_all {
+        let (nodes, errors) = get_nodes_with_errors(&schema, &options, &mut tracer);
+        if !errors.is_empty() {
+            errors.iter().for_each(|e| error!("{}", e));
+            error!("{} statements failed, nothing was written", errors.len());
+            std::process::exit(1);
+        }
+        nodes
+    } else {
+        get_nodes_traced(&schema, &options, &mut tracer)
+    };
+
+    if options.validate {
+        validate(&nodes)
+            .iter()
+            .for_each(|warning| warn!("{}", warning));
+    }
+
+    if options.flag_public_grants {
+        public_grants(&nodes)
+            .iter()
+            .for_each(|warning| warn!("{}", warning));
+    }
+
+    // Files of several objects are what `--flat` asks for
+    if !options.flat {
+        path_collisions(&nodes)
+            .iter()
+            .for_each(|warning| warn!("{}", warning));
+    }
 
-    let out_dir = supabase_dir.join("schemas");
+    // An absolute output directory replaces the Supabase directory
+    let out_dir = supabase_dir.join(cli.output_dir.as_deref().unwrap_or(Path::new("schemas")));
+    let layout = layout(&options);
 
-    // remove the existing schemas directory if it exists
-    let _ = fs::remove_dir_all(&out_dir);
+    if cli.dry_run {
+        let mut sink = MemorySink::default();
+        write_nodes_to(
+            &nodes,
+            &out_dir,
+            &options,
+            layout.as_ref(),
+            &mut tracer,
+            &mut sink,
+        );
+        sink.files
+            .keys()
+            .for_each(|path| info!("Would write {}", path.display()));
+        return;
+    }
 
-    write_nodes(&nodes, &out_dir);
+    let clean_dir = match &options.prefix {
+        Some(prefix) => out_dir.join(prefix),
+        None => out_dir.clone(),
+    };
+
+    if cli.diff {
+        let mut sink = MemorySink::default();
+        write_nodes_to(
+            &nodes,
+            &out_dir,
+            &options,
+            layout.as_ref(),
+            &mut tracer,
+            &mut sink,
+        );
+        let changes = diff_tree(&sink.files, &clean_dir);
+        changes.iter().for_each(|change| match change {
+            FileChange::Added(path) => info!("Added {}", path.display()),
+            FileChange::Removed(path) => info!("Removed {}", path.display()),
+            FileChange::Modified(path, diff) => {
+                info!("Modified {}", path.display());
+                print!("{}", diff);
+            }
+        });
+        if !changes.is_empty() {
+            error!(
+                "{} files differ from {}",
+                changes.len(),
+                clean_dir.display()
+            );
+            std::process::exit(1);
+        }
+        info!("{} is up to date", clean_dir.display());
+        return;
+    }
+
+    // The existing output is replaced, but the trees of other prefixes are left alone. Files are
+    // written to a staging directory first, so that a failed run leaves the previous output intact.
+    let replace = !options.append_new_only && !options.sync && !cli.no_clean;
+    let staging = staging_dir(&clean_dir);
+
+    if replace {
+        // A misconfigured output directory like `.` must not delete the project
+        if let (Ok(clean_dir), Ok(supabase_dir)) =
+            (clean_dir.canonicalize(), supabase_dir.canonicalize())
+            && supabase_dir.starts_with(&clean_dir)
+        {
+            error!(
+                "Refusing to delete {} as it contains the Supabase directory, use another output directory or --no-clean",
+                clean_dir.display()
+            );
+            std::process::exit(1);
+        }
+
+        // Left behind by a run that failed
+        let _ = fs::remove_dir_all(&staging);
+        if options.emit_test_stubs {
+            copy_test_stubs(&clean_dir, &staged(&staging, &options));
+        }
+    }
+
+    let write_dir = if replace { &staging } else { &out_dir };
+    info!("Writing files to {}...", out_dir.display());
+    let paths = write_nodes(&nodes, write_dir, &options, layout.as_ref(), &mut tracer);
+
+    if let Some(command) = &cli.format_cmd {
+        info!("Formatting the files with `{}`...", command);
+        let failed = paths
+            .iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|path| {
+                let result = format_file(path, command);
+                if let Err(e) = &result {
+                    warn!("Failed to format {}: {}", path.display(), e);
+                }
+                result.is_err()
+            })
+            .count();
+        if failed > 0 && cli.strict {
+            error!("The formatter failed on {} files", failed);
+            std::process::exit(1);
+        }
+    }
+
+    if cli.verify {
+        info!("Verifying the output...");
+        let written = paths
+            .iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|path| fs::read_to_string(path).expect("Failed to read generated file"))
+            .collect::<Vec<_>>();
+
+        let missing = missing_statements(&schema, &written, &options);
+        if !missing.is_empty() {
+            missing
+                .iter()
+                .for_each(|sql| error!("Statement missing from the output: {}", sql));
+            std::process::exit(1);
+        }
+    }
+
+    if replace {
+        let staged_dir = staged(&staging, &options);
+        fs::create_dir_all(&staged_dir).expect("Failed to create staging directory");
+        fs::create_dir_all(&out_dir).expect("Failed to create output directory");
+        replace_dir(&staged_dir, &clean_dir).unwrap_or_else(|e| {
+            panic!(
+                "Failed to move the output into {}: {}",
+                clean_dir.display(),
+                e
+            )
+        });
+        let _ = fs::remove_dir_all(&staging);
+    }
 
-    println!("Schema initialization completed successfully!");
+    info!("Schema initialization completed successfully!");
 }
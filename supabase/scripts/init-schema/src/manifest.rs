@@ -0,0 +1,56 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::locations::StatementLocation;
+
+/// A generated file and the object it contains
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    /// Path of the file, relative to the output directory
+    pub path: PathBuf,
+    pub kind: &'static str,
+    pub schema: Option<String>,
+    pub name: String,
+    /// Number of statements written to the file
+    pub statements: usize,
+}
+
+/// Index of all files written by a run, sorted by path
+#[derive(Debug, Default)]
+pub struct Manifest {
+    entries: BTreeMap<PathBuf, ManifestEntry>,
+}
+
+impl Manifest {
+    /// Record a statement written to `path`. The first node written to a file determines the
+    /// object it is listed as.
+    pub fn record(&mut self, path: &Path, out_dir: &Path, node: &StatementLocation) {
+        let relative = path.strip_prefix(out_dir).unwrap_or(path).to_path_buf();
+
+        self.entries
+            .entry(relative.clone())
+            .or_insert_with(|| ManifestEntry {
+                path: relative,
+                kind: node.category(),
+                schema: node.schema().map(|s| s.to_string()),
+                name: node.name().to_string(),
+                statements: 0,
+            })
+            .statements += 1;
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &ManifestEntry> {
+        self.entries.values()
+    }
+
+    /// The entries as a pretty-printed JSON array
+    pub fn to_json(&self) -> String {
+        let entries = self.entries().collect::<Vec<_>>();
+        let json = serde_json::to_string_pretty(&entries).expect("Failed to serialize manifest");
+        format!("{}\n", json)
+    }
+}
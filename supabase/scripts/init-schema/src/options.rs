@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+/// How identifiers are quoted in statements that are reconstructed rather than copied from the
+/// dump
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum QuoteStyle {
+    /// Quote every identifier
+    #[default]
+    Always,
+    /// Only quote identifiers that require it, e.g. reserved words or mixed case
+    Minimal,
+}
+
+/// How much the binary logs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Only errors
+    Quiet,
+    /// The phases of a run and warnings
+    #[default]
+    Normal,
+    /// Also the file and kind of every statement
+    Verbose,
+}
+
+/// Options that control how the dump is split into files
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Write grants, policies and RLS enablement into a separate `security/` tree that mirrors
+    /// the schema tree
+    pub split_security: bool,
+    /// Rewrite `EXECUTE PROCEDURE` in trigger definitions to `EXECUTE FUNCTION`
+    pub normalize_trigger_exec: bool,
+    /// Format every statement consistently by running it through the deparser before writing
+    pub deparse: bool,
+    /// Write a JSON index of all generated files to this path, relative to the output directory
+    pub manifest: Option<PathBuf>,
+    /// Warn about references to objects that are missing from the dump
+    pub validate: bool,
+    /// How identifiers are quoted in the statements that are reconstructed, like comments and
+    /// grants split per object
+    pub quote_style: QuoteStyle,
+    /// Keep the existing output and only write files for objects that do not have one yet
+    pub append_new_only: bool,
+    /// Print how every statement was routed, including the lookups that decided it
+    pub trace: bool,
+    /// Write an `__init__.sql` into every directory that includes its statement files in order
+    pub dir_index: bool,
+    /// Write a `_order.sql` into every schema directory that includes all files of the schema in
+    /// an order they can be applied in
+    pub schema_order: bool,
+    /// Keep the `OWNER TO` statements of tables, views and sequences instead of skipping them
+    pub keep_owners: bool,
+    /// How much the binary logs, set by `--quiet` and `--verbose`
+    pub verbosity: Verbosity,
+    /// Warn about grants to the `PUBLIC` pseudo-role
+    pub flag_public_grants: bool,
+    /// Rewrite the existing output in place and remove the files of objects that no longer exist,
+    /// instead of deleting the output directory first
+    pub sync: bool,
+    /// Write the signature of every object to this path, relative to the output directory
+    pub lock: Option<PathBuf>,
+    /// Write the whole tree into this subdirectory of the output directory, e.g. to keep the
+    /// schemas of several projects apart
+    pub prefix: Option<PathBuf>,
+    /// Leave unsupported statements out instead of aborting, they are logged as warnings
+    pub skip_unsupported: bool,
+    /// Only write objects of these schemas. Statements that do not belong to a schema, like
+    /// setup statements and casts, are always written. Empty means all schemas.
+    pub schemas: Vec<String>,
+    /// Write a pgTAP test stub next to every function and every table with policies, unless
+    /// the stub already exists
+    pub emit_test_stubs: bool,
+    /// Add a directory level named after the first character of every object, e.g.
+    /// `tables/u/users.sql`
+    pub bucket_by_initial: bool,
+    /// Write a single file per schema with all of its objects in the order of the dump
+    pub flat: bool,
+    /// Leave out `CREATE SCHEMA` statements, for databases where the schemas already exist
+    pub skip_schema_create: bool,
+    /// psql meta-commands to strip from the dump in addition to `DEFAULT_META_COMMANDS`, without
+    /// the backslash, e.g. `set`
+    pub meta_commands: Vec<String>,
+    /// Leave out `SET` and `RESET` statements of these variables, e.g. `statement_timeout`
+    pub skip_settings: Vec<String>,
+    /// Write `CREATE ROLE`, `ALTER ROLE` and role memberships to `roles/<name>.sql` instead of
+    /// skipping them
+    pub include_roles: bool,
+    /// Start every file with the session settings of the dump, like `search_path`, so that it
+    /// can be run on its own
+    pub set_header: bool,
+    /// Move foreign keys defined in `CREATE TABLE` into separate `ALTER TABLE ... ADD CONSTRAINT`
+    /// statements, like the ones of `pg_dump`
+    pub extract_inline_fkeys: bool,
+    /// Fail on comments and grants of objects in schemas without any object in the dump, instead
+    /// of skipping them with a warning
+    pub strict_schemas: bool,
+    /// Write the comments of every file after its other statements, also in files that keep the
+    /// order of the dump, like those of `flat`
+    pub comments_last: bool,
+}
+
+/// psql meta-commands that `pg_dump` writes into dumps. They are not SQL, so they are stripped
+/// before the dump is parsed.
+pub const DEFAULT_META_COMMANDS: &[&str] = &["connect", "c", "restrict", "unrestrict"];
+
+impl Options {
+    /// Whether objects of `schema` are written
+    pub fn includes_schema(&self, schema: &str) -> bool {
+        self.schemas.is_empty() || self.schemas.iter().any(|s| s == schema)
+    }
+}
@@ -1,27 +1,552 @@
 use crate::locations::{
-    Aggregate, CompositeType, EnablePolicy, Enum, ForeignKey, Function, Index, Operator, Policy,
-    Schema, Sequence, Setup, StatementLocation, Table, Trigger, TriggerFunction, View,
+    Aggregate, Cast, Collation, CompositeType, DefaultPrivileges, DropStatement, EnablePolicy,
+    Enum, Extension, ForeignKey, ForeignServer, ForeignTable, Function, Index, Operator, Partition,
+    Policy, Role, Schema, Sequence, Setup, StatementLocation, Table, TextSearch, Trigger,
+    TriggerFunction, UserMapping, View,
 };
-use pg_query::protobuf::ObjectType;
-use pg_query::{NodeEnum, Node};
+use crate::options::{DEFAULT_META_COMMANDS, Options, QuoteStyle};
+use crate::trace::Tracer;
+use pg_query::protobuf::{
+    AlterDefaultPrivilegesStmt, AlterTableCmd, AlterTableStmt, AlterTableType, ConstrType,
+    Constraint, CreateStmt, DefElem, DropBehavior, DropStmt, GrantStmt, KeywordKind, List,
+    ObjectType, ResTarget, RoleSpec, RoleSpecType, SelectStmt, String as PgString, Token,
+};
+use pg_query::{Node, NodeEnum, NodeRef};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+/// Why a statement of the dump was not written to a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseIssue {
+    /// The statement is not supported yet, e.g. an unknown kind of `ALTER TABLE`
+    Unsupported(String),
+    /// The statement is left out on purpose, e.g. a `RESET ALL`
+    Skipped(&'static str),
+}
 
-pub fn get_nodes(sql: &str) -> Vec<StatementLocation> {
-    let mut nodes: Vec<StatementLocation> = Vec::new();
+/// The nodes of a dump, the number of the statement each node was routed from and the statements
+/// that were not written with their number
+type Routed = (
+    Vec<StatementLocation>,
+    Vec<usize>,
+    Vec<(usize, String, ParseIssue)>,
+);
+
+impl fmt::Display for ParseIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseIssue::Unsupported(what) => write!(f, "Unsupported {}", what),
+            ParseIssue::Skipped(reason) => write!(f, "Skipped {}", reason),
+        }
+    }
+}
 
-    pg_query::split_with_parser(sql)
-        .expect("Failed to parse SQL")
+/// A statement of the dump that could not be routed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based number of the statement in the dump
+    pub number: usize,
+    pub sql: String,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Statement {} failed: {}: '{}'",
+            self.number,
+            self.message,
+            excerpt(&self.sql)
+        )
+    }
+}
+
+pub fn get_nodes(sql: &str, options: &Options) -> Vec<StatementLocation> {
+    get_nodes_traced(sql, options, &mut Tracer::default())
+}
+
+/// Like `get_nodes`, but records every routing decision in `tracer`
+pub fn get_nodes_traced(
+    sql: &str,
+    options: &Options,
+    tracer: &mut Tracer,
+) -> Vec<StatementLocation> {
+    let (nodes, _, issues) = collect_nodes(sql, options, tracer, !options.skip_unsupported, None);
+
+    issues
         .iter()
-        .for_each(|sql| {
-            parse(sql, &mut nodes);
+        .filter(|(_, _, issue)| matches!(issue, ParseIssue::Unsupported(_)))
+        .for_each(|(index, sql, issue)| {
+            log::warn!("{} in statement {}: '{}'", issue, index, excerpt(sql))
         });
 
     nodes
 }
 
-fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
-    let node = parse_sql(sql);
+/// Like `get_nodes`, but does not stop at unsupported statements. Also returns every statement
+/// that was not written to a file with its 1-based number in the dump and the reason.
+pub fn get_nodes_with_issues(
+    sql: &str,
+    options: &Options,
+) -> (Vec<StatementLocation>, Vec<(usize, String, ParseIssue)>) {
+    let (nodes, _, issues) = collect_nodes(sql, options, &mut Tracer::default(), false, None);
+    (nodes, issues)
+}
+
+/// Like `get_nodes_with_issues`, but also returns the 1-based number of the statement in the dump
+/// that each node was routed from, in the order of the nodes
+pub fn get_nodes_with_origins(sql: &str, options: &Options) -> Routed {
+    collect_nodes(sql, options, &mut Tracer::default(), false, None)
+}
+
+/// Like `get_nodes_traced`, but continues past every statement that fails or is unsupported and
+/// returns them instead, so that all of them can be fixed in one go. Failed statements have no
+/// node.
+pub fn get_nodes_with_errors(
+    sql: &str,
+    options: &Options,
+    tracer: &mut Tracer,
+) -> (Vec<StatementLocation>, Vec<ParseError>) {
+    let mut errors = Vec::new();
+    let (nodes, _, issues) = collect_nodes(sql, options, tracer, false, Some(&mut errors));
+
+    errors.extend(
+        issues
+            .into_iter()
+            .filter_map(|(number, sql, issue)| match issue {
+                ParseIssue::Unsupported(_) => Some(ParseError {
+                    number,
+                    sql,
+                    message: issue.to_string(),
+                }),
+                ParseIssue::Skipped(_) => None,
+            }),
+    );
+    errors.sort_by_key(|e| e.number);
+
+    (nodes, errors)
+}
+
+/// The location of a single statement, e.g. to tell in an editor which file it belongs in.
+/// `context` are statements it may refer to, like the table of a comment. A comment or grant on
+/// an object that is not part of them is assumed to be on a table, function or type of that name.
+/// A statement that can't be routed, e.g. an `ALTER FUNCTION` of a function that is not part of
+/// them, is returned as an error.
+pub fn classify(
+    sql: &str,
+    context: &[StatementLocation],
+    options: &Options,
+) -> Result<StatementLocation, ParseError> {
+    let error = |message: String| ParseError {
+        number: 1,
+        sql: sql.trim().to_string(),
+        message,
+    };
+
+    let stmts = pg_query::parse(sql)
+        .map_err(|e| error(e.to_string()))?
+        .protobuf
+        .stmts;
+    let node = match stmts.as_slice() {
+        [stmt] => stmt.stmt.as_ref().and_then(|n| n.node.clone()),
+        _ => {
+            return Err(error(format!(
+                "Expected a single statement, found {}",
+                stmts.len()
+            )));
+        }
+    };
+
+    let mut nodes = context.to_vec();
+    let mut issues = Vec::new();
+    if let Some(placeholder) = node.as_ref().and_then(placeholder)
+        && !nodes
+            .iter()
+            .any(|n| n.schema() == placeholder.schema() && n.name() == placeholder.name())
+    {
+        nodes.push(placeholder);
+    }
+    let first = nodes.len();
+    parse(
+        sql,
+        &mut nodes,
+        options,
+        &mut Tracer::default(),
+        &mut issues,
+    );
+
+    if let Some((_, issue)) = issues.into_iter().next() {
+        return Err(error(issue.to_string()));
+    }
+    nodes
+        .into_iter()
+        .nth(first)
+        .ok_or_else(|| error("Statement has no location".to_string()))
+}
+
+/// The object that a comment or grant refers to as the statement that `classify` assumes created
+/// it, as it can't tell e.g. a table from a view by its name. Types are assumed to be composite.
+fn placeholder(node: &NodeEnum) -> Option<StatementLocation> {
+    let (objtype, object) = match node {
+        NodeEnum::CommentStmt(c) => (c.objtype(), c.object.as_deref()?),
+        NodeEnum::GrantStmt(g) => (g.objtype(), g.objects.first()?),
+        _ => return None,
+    };
+    if !matches!(
+        objtype,
+        ObjectType::ObjectTable
+            | ObjectType::ObjectColumn
+            | ObjectType::ObjectView
+            | ObjectType::ObjectMatview
+            | ObjectType::ObjectForeignTable
+            | ObjectType::ObjectFunction
+            | ObjectType::ObjectProcedure
+            | ObjectType::ObjectAggregate
+            | ObjectType::ObjectType
+            | ObjectType::ObjectDomain
+    ) {
+        return None;
+    }
+    let names = match &object.node {
+        Some(NodeEnum::RangeVar(r)) => vec![schema_or_public(&r.schemaname), r.relname.clone()],
+        _ => object_names(object),
+    };
+
+    // Columns are named with their table, all other objects on their own
+    let names = match objtype {
+        ObjectType::ObjectColumn => &names[..names.len().saturating_sub(1)],
+        _ => &names[..],
+    };
+    let (schema, name) = match names {
+        [name] => ("public".to_string(), name.clone()),
+        [schema, name] => (schema.clone(), name.clone()),
+        _ => return None,
+    };
+
+    let sql = String::new();
+    Some(match objtype {
+        ObjectType::ObjectTable | ObjectType::ObjectColumn => StatementLocation::Table(Table {
+            schema,
+            name,
+            sql,
+            inherits: Vec::new(),
+            identity_sequences: Vec::new(),
+            constraints: Vec::new(),
+        }),
+        ObjectType::ObjectView | ObjectType::ObjectMatview => {
+            StatementLocation::View(View { schema, name, sql })
+        }
+        ObjectType::ObjectForeignTable => {
+            StatementLocation::ForeignTable(ForeignTable { schema, name, sql })
+        }
+        ObjectType::ObjectFunction | ObjectType::ObjectProcedure => {
+            StatementLocation::Function(Function { schema, name, sql })
+        }
+        ObjectType::ObjectAggregate => {
+            StatementLocation::Aggregate(Aggregate { schema, name, sql })
+        }
+        ObjectType::ObjectType | ObjectType::ObjectDomain => {
+            StatementLocation::CompositeType(CompositeType { schema, name, sql })
+        }
+        _ => return None,
+    })
+}
+
+/// The message of a caught panic
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| payload.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown error")
+        .to_string()
+}
+
+/// Parse all statements of the dump. If `strict` is set, an unsupported statement panics. A
+/// statement that fails panics too, unless `errors` is given to collect it. Also returns the
+/// number of the statement each node was routed from.
+fn collect_nodes(
+    sql: &str,
+    options: &Options,
+    tracer: &mut Tracer,
+    strict: bool,
+    mut errors: Option<&mut Vec<ParseError>>,
+) -> Routed {
+    let mut nodes: Vec<StatementLocation> = Vec::new();
+    let mut origins = Vec::new();
+    let mut issues = Vec::new();
+    let sql = strip_meta_commands(sql, options);
+
+    let statements = match split_statements(&sql) {
+        Ok(statements) => statements,
+        Err(e) => {
+            let error = ParseError {
+                number: 1,
+                sql: sql.trim().to_string(),
+                message: format!("Failed to split the dump: {}", e),
+            };
+            match errors {
+                Some(errors) => {
+                    errors.push(error);
+                    return (nodes, origins, issues);
+                }
+                None => panic!("{}", error),
+            }
+        }
+    };
+
+    statements.iter().enumerate().for_each(|(index, sql)| {
+        // Numbered from 1, like the statements in an editor
+        let number = index + 1;
+        let mut statement_issues = Vec::new();
+
+        // Most errors are panics deep inside the routing, so they are caught to add which
+        // statement of a possibly huge dump failed. Collected errors are reported at the end,
+        // so the default output of their panics is noise.
+        let route = || parse(sql, &mut nodes, options, tracer, &mut statement_issues);
+        let result = match errors {
+            Some(_) => catch_quietly(route),
+            None => panic::catch_unwind(AssertUnwindSafe(route)),
+        };
+        origins.resize(nodes.len(), number);
+        if let Err(payload) = result {
+            let error = ParseError {
+                number,
+                sql: sql.trim().to_string(),
+                message: panic_message(payload.as_ref()),
+            };
+            match errors.as_deref_mut() {
+                Some(errors) => {
+                    errors.push(error);
+                    return;
+                }
+                None => panic!("{}", error),
+            }
+        }
+
+        statement_issues.into_iter().for_each(|(stmt_sql, issue)| {
+            if strict && matches!(issue, ParseIssue::Unsupported(_)) {
+                panic!(
+                    "{}",
+                    ParseError {
+                        number,
+                        sql: stmt_sql,
+                        message: issue.to_string(),
+                    }
+                );
+            }
+            issues.push((number, stmt_sql, issue));
+        });
+    });
+    resolve_drops(&mut nodes);
+
+    (nodes, origins, issues)
+}
+
+/// Split the dump into statements. A dump with a syntax error is split by the scanner instead of
+/// the parser, so that only the statement with the error fails.
+pub(crate) fn split_statements(sql: &str) -> Result<Vec<&str>, pg_query::Error> {
+    if let Ok(statements) = pg_query::split_with_parser(sql) {
+        return Ok(statements);
+    }
+
+    // The scanner stops at e.g. an unbalanced parenthesis, so the rest of the dump is one more
+    // statement, which fails as well
+    let mut statements = pg_query::split_with_scanner(sql)?;
+    let end = statements.last().map_or(0, |last| {
+        last.as_ptr() as usize - sql.as_ptr() as usize + last.len()
+    });
+    let rest = sql[end..].trim_start_matches(';');
+    if !rest.trim().is_empty() {
+        statements.push(rest);
+    }
+    Ok(statements)
+}
+
+thread_local! {
+    /// Whether a panic of this thread is caught by `catch_quietly`
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Catch a panic of `f` without its default output, for panics that are reported otherwise. The
+/// panic hook is wrapped once instead of being swapped, so panics of other threads and of this one
+/// outside of `f` are still printed.
+fn catch_quietly<T>(f: impl FnOnce() -> T) -> std::thread::Result<T> {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !QUIET.get() {
+                hook(info);
+            }
+        }));
+    });
+
+    QUIET.set(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    QUIET.set(false);
+    result
+}
+
+/// The start of a statement for error messages, as a statement may be very long, e.g. a function
+pub(crate) fn excerpt(sql: &str) -> String {
+    let sql = sql.trim();
+    match sql.char_indices().nth(200) {
+        Some((end, _)) => format!("{}...", &sql[..end]),
+        None => sql.to_string(),
+    }
+}
+
+/// Remove the lines of psql meta-commands like `\connect` or `\restrict` from the dump, as the
+/// parser only understands SQL. Any other meta-command is left in place and fails to parse.
+pub(crate) fn strip_meta_commands(sql: &str, options: &Options) -> String {
+    sql.lines()
+        .filter(|line| {
+            let Some(command) = line.trim_start().strip_prefix('\\') else {
+                return true;
+            };
+            let name = command.split_whitespace().next().unwrap_or_default();
+            let strip = DEFAULT_META_COMMANDS.contains(&name)
+                || options.meta_commands.iter().any(|c| c == name);
+            if strip {
+                log::warn!("Skipping psql meta-command: '{}'", line.trim());
+            }
+            !strip
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse(
+    sql: &str,
+    nodes: &mut Vec<StatementLocation>,
+    options: &Options,
+    tracer: &mut Tracer,
+    issues: &mut Vec<(String, ParseIssue)>,
+) {
+    parse_statements(sql)
+        .into_iter()
+        .for_each(|(stmt_sql, node)| {
+            tracer.statement(&stmt_sql, &node);
+
+            // The object can't be found if its schema was not dumped, e.g. when dumping only some
+            // schemas, which would otherwise fail with a confusing lookup error
+            if let Some((statement, name, schema)) = undumped_reference(&node, nodes) {
+                let message = format!(
+                    "{} references {} but schema '{}' was not dumped",
+                    statement, name, schema
+                );
+                if options.strict_schemas {
+                    panic!("{}", message);
+                }
+                log::warn!("Skipping statement: {}", message);
+                issues.push((
+                    stmt_sql.trim().to_string(),
+                    ParseIssue::Skipped("reference to a schema that was not dumped"),
+                ));
+                return;
+            }
+
+            if let Err(issue) = route(&stmt_sql, node, nodes, options, tracer, issues) {
+                issues.push((stmt_sql.trim().to_string(), issue));
+            }
+            tracer.locations(nodes);
+        });
+}
+
+/// Kind of statement, qualified name and schema of the first object that a comment or grant
+/// references in a schema without any object in `nodes`
+fn undumped_reference(
+    node: &NodeEnum,
+    nodes: &[StatementLocation],
+) -> Option<(&'static str, String, String)> {
+    let (statement, names) = match node {
+        NodeEnum::CommentStmt(c) => {
+            // Names of columns, policies, triggers and constraints include their table
+            let unqualified = match c.objtype() {
+                ObjectType::ObjectColumn
+                | ObjectType::ObjectPolicy
+                | ObjectType::ObjectTrigger
+                | ObjectType::ObjectTabconstraint
+                | ObjectType::ObjectRule => 2,
+                ObjectType::ObjectTable
+                | ObjectType::ObjectView
+                | ObjectType::ObjectMatview
+                | ObjectType::ObjectSequence
+                | ObjectType::ObjectForeignTable
+                | ObjectType::ObjectIndex
+                | ObjectType::ObjectFunction
+                | ObjectType::ObjectProcedure
+                | ObjectType::ObjectAggregate
+                | ObjectType::ObjectType
+                | ObjectType::ObjectDomain => 1,
+                _ => return None,
+            };
+            let mut names = c
+                .object
+                .as_ref()
+                .map(|o| object_names(o))
+                .unwrap_or_default();
+            if names.len() <= unqualified {
+                names.insert(0, "public".to_string());
+            }
+            ("COMMENT", vec![names])
+        }
+        NodeEnum::GrantStmt(g)
+            if g.targtype() == pg_query::protobuf::GrantTargetType::AclTargetObject =>
+        {
+            let names = g
+                .objects
+                .iter()
+                .filter_map(|o| match &o.node {
+                    Some(NodeEnum::RangeVar(r)) => {
+                        Some(vec![schema_or_public(&r.schemaname), r.relname.clone()])
+                    }
+                    Some(NodeEnum::ObjectWithArgs(_)) => {
+                        let mut names = object_names(o);
+                        if names.len() == 1 {
+                            names.insert(0, "public".to_string());
+                        }
+                        Some(names)
+                    }
+                    _ => None,
+                })
+                .collect();
+            (if g.is_grant { "GRANT" } else { "REVOKE" }, names)
+        }
+        _ => return None,
+    };
+
+    let dumped = nodes
+        .iter()
+        .filter_map(StatementLocation::schema)
+        .collect::<HashSet<_>>();
+    names
+        .into_iter()
+        .find(|names| !dumped.contains(names[0].as_str()))
+        .map(|names| (statement, names.join("."), names[0].clone()))
+}
+
+/// Add the location of a statement to `nodes`, or return why it has none
+fn route(
+    sql: &str,
+    node: NodeEnum,
+    nodes: &mut Vec<StatementLocation>,
+    options: &Options,
+    tracer: &mut Tracer,
+    issues: &mut Vec<(String, ParseIssue)>,
+) -> Result<(), ParseIssue> {
     match node {
         pg_query::NodeEnum::CreateSchemaStmt(n) => {
+            // Comments and grants of the schema are still routed to its index
+            if options.skip_schema_create {
+                return Err(ParseIssue::Skipped("schema creation"));
+            }
+
             let schema_name = n.schemaname.to_string();
             nodes.push(StatementLocation::Schema(Schema {
                 name: schema_name.clone(),
@@ -37,32 +562,78 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
 
                 if let NodeEnum::List(l) = list {
                     let items = extract_names(&l.items, "column comment");
-                    validate_item_count(&items, 3, "column comment");
-
-                    let schema = &items[0];
-                    let table_name = &items[1];
-                    let column_name = &items[2];
-
-                    if find_table(nodes, schema, table_name) {
+                    let (schema, table_name, column_name) = match items.as_slice() {
+                        [schema, table_name, column_name] => {
+                            (schema.as_str(), table_name, column_name)
+                        }
+                        [table_name, column_name] => ("public", table_name, column_name),
+                        _ => panic!(
+                            "Expected 2 or 3 items in column comment, found {}",
+                            items.len()
+                        ),
+                    };
+
+                    if find_table(nodes, schema, table_name, tracer) {
                         nodes.push(StatementLocation::Table(Table {
                             name: table_name.to_string(),
                             schema: schema.to_string(),
                             sql: format!(
-                                "COMMENT ON COLUMN \"{}\".\"{}\".\"{}\" IS E'{}';",
-                                schema, table_name, column_name, c.comment.replace("'", "''")
+                                "COMMENT ON COLUMN {} IS {};",
+                                quote_qualified(
+                                    &[schema, table_name, column_name],
+                                    options.quote_style
+                                ),
+                                comment_text(&c.comment)
                             ),
+                            inherits: Vec::new(),
+                            identity_sequences: Vec::new(),
+                            constraints: Vec::new(),
                         }));
-                    } else if find_view(nodes, schema, table_name) {
+                    } else if find_view(nodes, schema, table_name, tracer) {
                         nodes.push(StatementLocation::View(View {
                             name: table_name.to_string(),
                             schema: schema.to_string(),
                             sql: format!(
-                                "COMMENT ON COLUMN \"{}\".\"{}\".\"{}\" IS E'{}';",
-                                schema, table_name, column_name, c.comment.replace("'", "''")
+                                "COMMENT ON COLUMN {} IS {};",
+                                quote_qualified(
+                                    &[schema, table_name, column_name],
+                                    options.quote_style
+                                ),
+                                comment_text(&c.comment)
+                            ),
+                        }));
+                    } else if find_foreign_table(nodes, schema, table_name, tracer) {
+                        nodes.push(StatementLocation::ForeignTable(ForeignTable {
+                            name: table_name.to_string(),
+                            schema: schema.to_string(),
+                            sql: format!(
+                                "COMMENT ON COLUMN {} IS {};",
+                                quote_qualified(
+                                    &[schema, table_name, column_name],
+                                    options.quote_style
+                                ),
+                                comment_text(&c.comment)
+                            ),
+                        }));
+                    } else if find_composite_type(nodes, schema, table_name, tracer) {
+                        // Attributes of composite types are columns too
+                        nodes.push(StatementLocation::CompositeType(CompositeType {
+                            name: table_name.to_string(),
+                            schema: schema.to_string(),
+                            sql: format!(
+                                "COMMENT ON COLUMN {} IS {};",
+                                quote_qualified(
+                                    &[schema, table_name, column_name],
+                                    options.quote_style
+                                ),
+                                comment_text(&c.comment)
                             ),
                         }));
                     } else {
-                        panic!("No table or view found for {}.{}", schema, table_name);
+                        return Err(ParseIssue::Unsupported(format!(
+                            "comment on a column of {}.{} which is not part of the dump",
+                            schema, table_name
+                        )));
                     }
                 } else {
                     panic!("Expected List node for column comment, found {:?}", list);
@@ -81,26 +652,31 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                     let schema = &items[0];
                     let function_name = &items[1];
 
-                    if find_trigger_function(nodes, schema, function_name) {
+                    if find_trigger_function(nodes, schema, function_name, tracer) {
                         nodes.push(StatementLocation::TriggerFunction(TriggerFunction {
                             name: function_name.to_string(),
                             schema: schema.to_string(),
                             sql: format!(
-                                "COMMENT ON FUNCTION \"{}\".\"{}\" IS E'{}';",
-                                schema, function_name, c.comment.replace("'", "''")
+                                "COMMENT ON FUNCTION {} IS {};",
+                                quote_qualified(&[schema, function_name], options.quote_style),
+                                comment_text(&c.comment)
                             ),
                         }));
-                    } else if find_function(nodes, schema, function_name) {
+                    } else if find_function(nodes, schema, function_name, tracer) {
                         nodes.push(StatementLocation::Function(Function {
                             name: function_name.to_string(),
                             schema: schema.to_string(),
                             sql: format!(
-                                "COMMENT ON FUNCTION \"{}\".\"{}\" IS E'{}';",
-                                schema, function_name, c.comment.replace("'", "''")
+                                "COMMENT ON FUNCTION {} IS {};",
+                                quote_qualified(&[schema, function_name], options.quote_style),
+                                comment_text(&c.comment)
                             ),
                         }));
                     } else {
-                        panic!("No trigger or function found for {}.{}", schema, function_name);
+                        return Err(ParseIssue::Unsupported(format!(
+                            "comment on function {}.{} which is not part of the dump",
+                            schema, function_name
+                        )));
                     }
                 } else {
                     panic!("Expected ObjectWithArgs for function comment, found {:?}", list);
@@ -113,7 +689,11 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
 
                 nodes.push(StatementLocation::Schema(Schema {
                     name: schema_name.to_string(),
-                    sql: format!("COMMENT ON SCHEMA \"{}\" IS E'{}';", schema_name, c.comment.replace("'", "''")),
+                    sql: format!(
+                        "COMMENT ON SCHEMA {} IS {};",
+                        quote_qualified(&[&schema_name], options.quote_style),
+                        comment_text(&c.comment)
+                    ),
                 }));
             }
             ObjectType::ObjectType => {
@@ -126,26 +706,31 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                     let items = extract_names(&obj.names, "type comment");
                     let (schema, type_name) = extract_schema_and_name(&items, "type comment");
 
-                    if find_enum(nodes, schema, type_name) {
+                    if find_enum(nodes, schema, type_name, tracer) {
                         nodes.push(StatementLocation::EnumNode(Enum {
                             name: type_name.to_string(),
                             schema: schema.to_string(),
                             sql: format!(
-                                "COMMENT ON TYPE \"{}\".\"{}\" IS E'{}';",
-                                schema, type_name, c.comment.replace("'", "''")
+                                "COMMENT ON TYPE {} IS {};",
+                                quote_qualified(&[schema, type_name], options.quote_style),
+                                comment_text(&c.comment)
                             ),
                         }));
-                    } else if find_composite_type(nodes, schema, type_name) {
+                    } else if find_composite_type(nodes, schema, type_name, tracer) {
                         nodes.push(StatementLocation::CompositeType(CompositeType {
                             name: type_name.to_string(),
                             schema: schema.to_string(),
                             sql: format!(
-                                "COMMENT ON TYPE \"{}\".\"{}\" IS E'{}';",
-                                schema, type_name, c.comment.replace("'", "''")
+                                "COMMENT ON TYPE {} IS {};",
+                                quote_qualified(&[schema, type_name], options.quote_style),
+                                comment_text(&c.comment)
                             ),
                         }));
                     } else {
-                        panic!("No type found for comment on {}.{}", schema, type_name);
+                        return Err(ParseIssue::Unsupported(format!(
+                            "comment on type {}.{} which is not part of the dump",
+                            schema, type_name
+                        )));
                     }
                 } else {
                     panic!("Expected TypeName for type comment, found {:?}", type_node);
@@ -161,33 +746,198 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                     let items = extract_names(&l.items, "table comment");
                     let (schema, table_name) = extract_schema_and_name(&items, "table comment");
 
-                    if find_table(nodes, schema, table_name) {
+                    if find_table(nodes, schema, table_name, tracer) {
                         nodes.push(StatementLocation::Table(Table {
                             name: table_name.to_string(),
                             schema: schema.to_string(),
                             sql: format!(
-                                "COMMENT ON TABLE \"{}\".\"{}\" IS '{}';",
-                                schema, table_name, c.comment
+                                "COMMENT ON TABLE {} IS {};",
+                                quote_qualified(&[schema, table_name], options.quote_style),
+                                comment_text(&c.comment)
                             ),
+                            inherits: Vec::new(),
+                            identity_sequences: Vec::new(),
+                            constraints: Vec::new(),
                         }));
-                    } else if find_view(nodes, schema, table_name) {
+                    } else if find_view(nodes, schema, table_name, tracer) {
                         nodes.push(StatementLocation::View(View {
                             name: table_name.to_string(),
                             schema: schema.to_string(),
                             sql: format!(
-                                "COMMENT ON VIEW \"{}\".\"{}\" IS '{}';",
-                                schema, table_name, c.comment
+                                "COMMENT ON VIEW {} IS {};",
+                                quote_qualified(&[schema, table_name], options.quote_style),
+                                comment_text(&c.comment)
                             ),
                         }));
                     } else {
-                        panic!("No table or view found for {}.{}", schema, table_name);
+                        return Err(ParseIssue::Unsupported(format!(
+                            "comment on table {}.{} which is not part of the dump",
+                            schema, table_name
+                        )));
                     }
                 } else {
                     panic!("Expected List for table comment, found {:?}", list);
                 }
             }
+            ObjectType::ObjectForeignTable => {
+                if let Some(NodeEnum::List(l)) = c.object.as_ref().and_then(|o| o.node.as_ref()) {
+                    let items = extract_names(&l.items, "foreign table comment");
+                    let (schema, table_name) =
+                        extract_schema_and_name(&items, "foreign table comment");
+
+                    if !find_foreign_table(nodes, schema, table_name, tracer) {
+                        return Err(ParseIssue::Unsupported(format!(
+                            "comment on foreign table {}.{} which is not part of the dump",
+                            schema, table_name
+                        )));
+                    }
+
+                    nodes.push(StatementLocation::ForeignTable(ForeignTable {
+                        name: table_name.to_string(),
+                        schema: schema.to_string(),
+                        sql: format!(
+                            "COMMENT ON FOREIGN TABLE {} IS {};",
+                            quote_qualified(&[schema, table_name], options.quote_style),
+                            comment_text(&c.comment)
+                        ),
+                    }));
+                } else {
+                    panic!(
+                        "Expected List for foreign table comment, found {:?}",
+                        c.object
+                    );
+                }
+            }
+            ObjectType::ObjectPolicy => {
+                if let Some(NodeEnum::List(l)) = c.object.as_ref().and_then(|o| o.node.as_ref()) {
+                    let items = extract_names(&l.items, "policy comment");
+                    let (table, policy_name) = items.split_at(items.len() - 1);
+                    let (schema, table_name) = extract_schema_and_name(table, "policy comment");
+                    let policy_name = &policy_name[0];
+
+                    nodes.push(StatementLocation::Policy(Policy {
+                        schema: schema.to_string(),
+                        name: policy_name.to_string(),
+                        table: table_name.to_string(),
+                        functions: Vec::new(),
+                        sql: format!(
+                            "COMMENT ON POLICY {} ON {} IS {};",
+                            quote_qualified(&[policy_name], options.quote_style),
+                            quote_qualified(&[schema, table_name], options.quote_style),
+                            comment_text(&c.comment)
+                        ),
+                    }));
+                } else {
+                    panic!("Expected List for policy comment, found {:?}", c.object);
+                }
+            }
+            ObjectType::ObjectTabconstraint => {
+                if let Some(NodeEnum::List(l)) = c.object.as_ref().and_then(|o| o.node.as_ref()) {
+                    let items = extract_names(&l.items, "constraint comment");
+                    let (table, constraint_name) = items.split_at(items.len() - 1);
+                    let (schema, table_name) = extract_schema_and_name(table, "constraint comment");
+                    let constraint_name = &constraint_name[0];
+
+                    let sql = format!(
+                        "COMMENT ON CONSTRAINT {} ON {} IS {};",
+                        quote_qualified(&[constraint_name], options.quote_style),
+                        quote_qualified(&[schema, table_name], options.quote_style),
+                        comment_text(&c.comment)
+                    );
+
+                    // Foreign keys are written to their own files after all tables, so the
+                    // comment has to follow them there
+                    let foreign_key = nodes.iter().find_map(|n| match n {
+                        StatementLocation::ForeignKey(fk)
+                            if fk.source_schema == schema
+                                && fk.source_table == *table_name
+                                && fk.constraint_name == *constraint_name =>
+                        {
+                            Some(fk.clone())
+                        }
+                        _ => None,
+                    });
+
+                    match foreign_key {
+                        Some(fk) => {
+                            nodes.push(StatementLocation::ForeignKey(ForeignKey { sql, ..fk }))
+                        }
+                        None => nodes.push(StatementLocation::Table(Table {
+                            schema: schema.to_string(),
+                            name: table_name.to_string(),
+                            sql,
+                            inherits: Vec::new(),
+                            identity_sequences: Vec::new(),
+                            constraints: Vec::new(),
+                        })),
+                    }
+                } else {
+                    panic!("Expected List for constraint comment, found {:?}", c.object);
+                }
+            }
+            ObjectType::ObjectForeignServer => {
+                let server_name = get_sval(
+                    &c.object
+                        .clone()
+                        .expect("Missing object in server comment")
+                        .node,
+                );
+
+                nodes.push(StatementLocation::ForeignServer(ForeignServer {
+                    sql: format!(
+                        "COMMENT ON SERVER {} IS {};",
+                        quote_qualified(&[&server_name], options.quote_style),
+                        comment_text(&c.comment)
+                    ),
+                    name: server_name,
+                }));
+            }
+            ObjectType::ObjectExtension => {
+                let extension_name = get_sval(
+                    &c.object
+                        .clone()
+                        .expect("Missing object in extension comment")
+                        .node,
+                );
+
+                // Extensions are not schema-qualified, their file is in the directory of the
+                // schema they were created in
+                let Some(schema) = find_extension(nodes, &extension_name, tracer) else {
+                    return Err(ParseIssue::Unsupported(format!(
+                        "comment on extension {} which is not part of the dump",
+                        extension_name
+                    )));
+                };
+
+                nodes.push(StatementLocation::Extension(Extension {
+                    schema,
+                    sql: format!(
+                        "COMMENT ON EXTENSION {} IS {};",
+                        quote_qualified(&[&extension_name], options.quote_style),
+                        comment_text(&c.comment)
+                    ),
+                    name: extension_name,
+                }));
+            }
+            kind @ (ObjectType::ObjectTsconfiguration
+            | ObjectType::ObjectTsdictionary
+            | ObjectType::ObjectTstemplate
+            | ObjectType::ObjectTsparser) => {
+                if let Some(NodeEnum::List(l)) = c.object.as_ref().and_then(|o| o.node.as_ref()) {
+                    let names = extract_names(&l.items, "text search comment");
+                    nodes.push(text_search(&names, kind, sql));
+                } else {
+                    panic!(
+                        "Expected List for text search comment, found {:?}",
+                        c.object
+                    );
+                }
+            }
             _ => {
-                panic!("Unsupported comment type: {:?}", c.objtype());
+                return Err(ParseIssue::Unsupported(format!(
+                    "comment type {:?}",
+                    c.objtype()
+                )));
             }
         },
         NodeEnum::CreateEnumStmt(n) => {
@@ -230,12 +980,58 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                     sql: sql.to_string(),
                 }));
             }
-            _ => panic!("Unsupported define statement kind: {:?}", n.kind()),
+            ObjectType::ObjectCollation => {
+                let names = extract_names(&n.defnames, "collation definition");
+                let schema = get_schema_or_default(&names);
+                let collation_name = names
+                    .last()
+                    .expect("Missing collation name in definition")
+                    .to_string();
+
+                nodes.push(StatementLocation::Collation(Collation {
+                    schema: schema.to_string(),
+                    name: collation_name,
+                    sql: sql.to_string(),
+                }));
+            }
+            kind @ (ObjectType::ObjectTsconfiguration
+            | ObjectType::ObjectTsdictionary
+            | ObjectType::ObjectTstemplate
+            | ObjectType::ObjectTsparser) => {
+                let names = extract_names(&n.defnames, "text search definition");
+                nodes.push(text_search(&names, kind, sql));
+            }
+            _ => {
+                return Err(ParseIssue::Unsupported(format!(
+                    "DefineStmt kind {:?}",
+                    n.kind()
+                )));
+            }
         },
+        NodeEnum::CreateCastStmt(n) => {
+            // The function of a cast is not looked up, casts are created after all functions
+            let source = type_name(
+                n.sourcetype
+                    .as_ref()
+                    .expect("Missing source type in CreateCastStmt"),
+            );
+            let target = type_name(
+                n.targettype
+                    .as_ref()
+                    .expect("Missing target type in CreateCastStmt"),
+            );
+
+            nodes.push(StatementLocation::Cast(Cast {
+                name: format!("{}__{}", source, target),
+                source,
+                target,
+                sql: sql.to_string(),
+            }));
+        }
         pg_query::NodeEnum::CompositeTypeStmt(n) => {
             let name = n.typevar.expect("Missing typevar in CompositeTypeStmt");
 
-            let schema = name.schemaname;
+            let schema = schema_or_public(&name.schemaname);
             let type_name = name.relname;
 
             nodes.push(StatementLocation::CompositeType(CompositeType {
@@ -246,7 +1042,7 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
         }
         pg_query::NodeEnum::ViewStmt(n) => {
             let rel = n.view.expect("Missing relation in ViewStmt");
-            let schema = rel.schemaname;
+            let schema = schema_or_public(&rel.schemaname);
             let view_name = rel.relname;
 
             nodes.push(StatementLocation::View(View {
@@ -259,44 +1055,165 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
             let name = n.policy_name;
             let table = n.table.expect("Missing table in CreatePolicyStmt");
 
-            let schema = table.schemaname;
+            let schema = schema_or_public(&table.schemaname);
             let relation_name = table.relname;
 
+            let expressions = [&n.qual, &n.with_check]
+                .into_iter()
+                .flatten()
+                .map(|e| e.as_ref())
+                .collect::<Vec<_>>();
+
             nodes.push(StatementLocation::Policy(Policy {
                 schema: schema.clone(),
                 name,
                 table: relation_name,
+                functions: called_functions(&expressions),
                 sql: sql.to_string(),
             }));
         }
         NodeEnum::CreateStmt(n) => {
-            let rel = n.relation.expect("Missing relation in CreateStmt");
-            let schema = rel.schemaname.clone();
+            let rel = n.relation.clone().expect("Missing relation in CreateStmt");
+            let schema = schema_or_public(&rel.schemaname);
             let table_name = rel.relname.clone();
 
+            // `CREATE TABLE ... PARTITION OF parent` lists the parent as its only inherited table
+            if n.partbound.is_some() {
+                let parent = match n.inh_relations.first().and_then(|r| r.node.as_ref()) {
+                    Some(NodeEnum::RangeVar(r)) => r,
+                    other => panic!("Expected RangeVar for partition parent, found {:?}", other),
+                };
+
+                nodes.push(StatementLocation::Partition(Partition {
+                    schema,
+                    name: table_name,
+                    parent_schema: schema_or_public(&parent.schemaname),
+                    parent: parent.relname.clone(),
+                    sql: sql.to_string(),
+                }));
+                return Ok(());
+            }
+
+            // The parents of `INHERITS` have to be created first
+            let inherits = n
+                .inh_relations
+                .iter()
+                .map(|r| match &r.node {
+                    Some(NodeEnum::RangeVar(r)) => {
+                        (schema_or_public(&r.schemaname), r.relname.clone())
+                    }
+                    other => panic!("Expected RangeVar for inherited table, found {:?}", other),
+                })
+                .collect::<Vec<_>>();
+
+            let identity_sequences = identity_sequences(
+                &schema,
+                &table_name,
+                n.table_elts.iter().flat_map(|e| match &e.node {
+                    Some(NodeEnum::ColumnDef(d)) => d
+                        .constraints
+                        .iter()
+                        .map(|c| (d.colname.as_str(), c))
+                        .collect(),
+                    _ => Vec::new(),
+                }),
+            );
+
+            if options.extract_inline_fkeys
+                && let Some((table, foreign_keys)) = extract_inline_foreign_keys(&n)
+            {
+                let table_sql = NodeEnum::CreateStmt(table)
+                    .deparse()
+                    .expect("Failed to deparse CREATE TABLE without foreign keys");
+                nodes.push(StatementLocation::Table(Table {
+                    schema: schema.clone(),
+                    name: table_name.clone(),
+                    sql: format!("{}{};", leading_comments(sql), table_sql),
+                    inherits,
+                    identity_sequences,
+                    constraints: Vec::new(),
+                }));
+
+                foreign_keys.into_iter().for_each(|c| {
+                    let pktable = c
+                        .pktable
+                        .as_ref()
+                        .expect("Missing target table for foreign key");
+                    let target_schema = schema_or_public(&pktable.schemaname);
+                    let target_table = pktable.relname.clone();
+                    let constraint_name = c.conname.clone();
+                    let alter = NodeEnum::AlterTableStmt(AlterTableStmt {
+                        relation: Some(rel.clone()),
+                        cmds: vec![Node {
+                            node: Some(NodeEnum::AlterTableCmd(Box::new(AlterTableCmd {
+                                subtype: AlterTableType::AtAddConstraint as i32,
+                                def: Some(Box::new(Node {
+                                    node: Some(NodeEnum::Constraint(Box::new(c))),
+                                })),
+                                behavior: DropBehavior::DropRestrict as i32,
+                                ..Default::default()
+                            }))),
+                        }],
+                        objtype: ObjectType::ObjectTable as i32,
+                        ..Default::default()
+                    })
+                    .deparse()
+                    .expect("Failed to deparse extracted foreign key");
+
+                    nodes.push(StatementLocation::ForeignKey(ForeignKey {
+                        constraint_name,
+                        source_schema: schema.clone(),
+                        source_table: table_name.clone(),
+                        target_schema,
+                        target_table,
+                        sql: format!("{};", alter),
+                    }));
+                });
+                return Ok(());
+            }
+
             nodes.push(StatementLocation::Table(Table {
                 schema,
                 name: table_name,
                 sql: sql.to_string(),
+                inherits,
+                identity_sequences,
+                constraints: Vec::new(),
             }));
         }
         NodeEnum::CreateTrigStmt(n) => {
-            let rel = n.relation.expect("Missing relation in CreateTrigStmt");
-            let schema = rel.schemaname.clone();
+            // Constraint triggers belong to the table they are defined on, not to the table of
+            // their `FROM` clause. Their deferral clauses are part of the SQL as written.
+            let rel = n
+                .relation
+                .clone()
+                .expect("Missing relation in CreateTrigStmt");
+            let schema = schema_or_public(&rel.schemaname);
             let table_name = rel.relname.clone();
 
             let func_names = extract_names(&n.funcname, "trigger function");
+            let function_schema = get_schema_or_default(&func_names).to_string();
             let function_name = func_names.last()
                 .expect("Missing function name in trigger")
                 .to_string();
             let trigger_name = n.trigname.clone();
 
+            // Older dumps use `EXECUTE PROCEDURE`, the deparser always emits `EXECUTE FUNCTION`
+            let sql = if options.normalize_trigger_exec && uses_execute_procedure(sql) {
+                NodeEnum::CreateTrigStmt(n)
+                    .deparse()
+                    .expect("Failed to deparse CreateTrigStmt")
+            } else {
+                sql.to_string()
+            };
+
             nodes.push(StatementLocation::Trigger(Trigger {
                 schema,
                 name: trigger_name,
                 table: table_name,
+                function_schema,
                 function: function_name,
-                sql: sql.to_string(),
+                sql,
             }));
         }
         NodeEnum::CreateFunctionStmt(n) => {
@@ -328,9 +1245,36 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                 }));
             }
         }
+        NodeEnum::AlterFunctionStmt(n) => {
+            let func = n
+                .func
+                .as_ref()
+                .expect("Missing function in AlterFunctionStmt");
+            let items = extract_names(&func.objname, "function alter");
+            let (schema, function_name) = extract_schema_and_name(&items, "function alter");
+
+            if find_trigger_function(nodes, schema, function_name, tracer) {
+                nodes.push(StatementLocation::TriggerFunction(TriggerFunction {
+                    name: function_name.to_string(),
+                    schema: schema.to_string(),
+                    sql: sql.to_string(),
+                }));
+            } else if find_function(nodes, schema, function_name, tracer) {
+                nodes.push(StatementLocation::Function(Function {
+                    name: function_name.to_string(),
+                    schema: schema.to_string(),
+                    sql: sql.to_string(),
+                }));
+            } else {
+                return Err(ParseIssue::Unsupported(format!(
+                    "change of function {}.{} which is not part of the dump",
+                    schema, function_name
+                )));
+            }
+        }
         pg_query::NodeEnum::IndexStmt(n) => {
             let rel = n.relation.expect("Missing relation in IndexStmt");
-            let schema = rel.schemaname;
+            let schema = schema_or_public(&rel.schemaname);
             let index_name = n.idxname;
             let table_name = rel.relname;
 
@@ -338,19 +1282,64 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                 schema: schema.clone(),
                 name: index_name,
                 table: table_name,
+                concurrent: n.concurrent,
                 sql: sql.to_string(),
             }));
         }
         pg_query::NodeEnum::AlterTableStmt(n) => {
-            let rel = n.relation.expect("Missing relation in AlterTableStmt");
-            let schema = rel.schemaname;
-            let table_name = rel.relname;
-
             let number_of_commands = n.cmds.len();
             if number_of_commands == 0 {
                 panic!("No commands in AlterTableStmt");
             }
 
+            let subtypes = n
+                .cmds
+                .iter()
+                .map(|cmd| match &cmd.node {
+                    Some(pg_query::NodeEnum::AlterTableCmd(c)) => c.subtype(),
+                    Some(other) => panic!("Unsupported {} in AlterTableStmt", node_kind(other)),
+                    None => panic!("Missing node in AlterTableStmt command"),
+                })
+                .collect::<Vec<_>>();
+
+            // Commands of different kinds may belong into different files, e.g. a column default
+            // and a foreign key, and every constraint is routed on its own, so we split them into
+            // one statement per command
+            if subtypes.iter().any(|s| *s != subtypes[0])
+                || (number_of_commands > 1
+                    && subtypes[0] == pg_query::protobuf::AlterTableType::AtAddConstraint)
+            {
+                split_alter_table(&n).iter().for_each(|cmd_sql| {
+                    parse(cmd_sql, nodes, options, tracer, issues);
+                });
+                return Ok(());
+            }
+
+            let rel = n
+                .relation
+                .clone()
+                .expect("Missing relation in AlterTableStmt");
+            let schema = schema_or_public(&rel.schemaname);
+            let table_name = rel.relname;
+
+            // All changes of a foreign table, e.g. its options, go into its own file
+            if n.objtype() == ObjectType::ObjectForeignTable
+                || find_foreign_table(nodes, &schema, &table_name, tracer)
+            {
+                if subtypes[0] == pg_query::protobuf::AlterTableType::AtChangeOwner
+                    && !options.keep_owners
+                {
+                    return Err(ParseIssue::Skipped("ownership change"));
+                }
+
+                nodes.push(StatementLocation::ForeignTable(ForeignTable {
+                    schema,
+                    name: table_name,
+                    sql: sql.to_string(),
+                }));
+                return Ok(());
+            }
+
             let cmd = n.cmds.first()
                 .expect("Missing command in AlterTableStmt")
                 .node.clone()
@@ -358,48 +1347,113 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
 
             match &cmd {
                 pg_query::NodeEnum::AlterTableCmd(c) => match c.subtype() {
-                    pg_query::protobuf::AlterTableType::AtColumnDefault => {
+                    // pg_dump sets the statistics of an expression index by column number
+                    pg_query::protobuf::AlterTableType::AtSetStatistics if n.objtype() == ObjectType::ObjectIndex => {
+                        match index_location(nodes, &schema, &table_name, sql) {
+                            Some(index) => nodes.push(index),
+                            None => {
+                                return Err(ParseIssue::Unsupported(format!(
+                                    "statistics of index {}.{} which is not part of the dump",
+                                    schema, table_name
+                                )));
+                            }
+                        }
+                    }
+                    pg_query::protobuf::AlterTableType::AtColumnDefault
+                    | pg_query::protobuf::AlterTableType::AtAddColumn
+                    // The persistence of the table is set by replaying the statement after its
+                    // definition, no other file depends on it
+                    | pg_query::protobuf::AlterTableType::AtSetLogged
+                    | pg_query::protobuf::AlterTableType::AtSetUnLogged
+                    // Tuning of a column, e.g. `SET STATISTICS 1000` or `SET STORAGE EXTERNAL`
+                    | pg_query::protobuf::AlterTableType::AtSetStatistics
+                    | pg_query::protobuf::AlterTableType::AtSetStorage
+                    | pg_query::protobuf::AlterTableType::AtSetOptions
+                    | pg_query::protobuf::AlterTableType::AtResetOptions => {
+                        // A sequence that is not owned by any table is written to the file of the
+                        // table whose default uses it so that it is created before the default
+                        if let Some((seq_schema, seq_name)) =
+                            c.def.as_deref().and_then(nextval_sequence)
+                        {
+                            adopt_sequence(nodes, &seq_schema, &seq_name, &schema, &table_name);
+                        }
+
+                        nodes.push(StatementLocation::Table(Table {
+                            schema: schema.clone(),
+                            name: table_name.clone(),
+                            sql: sql.to_string(),
+                            inherits: Vec::new(),
+                            identity_sequences: Vec::new(),
+                            constraints: Vec::new(),
+                        }));
+                    }
+                    // pg_dump turns identity columns into plain columns and adds the identity
+                    // afterwards, which creates the identity sequence. The sequence has no file of
+                    // its own, `identity_owner` finds it here for its grants and other statements.
+                    pg_query::protobuf::AlterTableType::AtAddIdentity
+                    | pg_query::protobuf::AlterTableType::AtSetIdentity
+                    | pg_query::protobuf::AlterTableType::AtDropIdentity => {
+                        let identity_sequences = identity_sequences(
+                            &schema,
+                            &table_name,
+                            n.cmds.iter().filter_map(|cmd| match &cmd.node {
+                                Some(NodeEnum::AlterTableCmd(c)) => Some((c.name.as_str(), c.def.as_deref()?)),
+                                _ => None,
+                            }),
+                        );
+
                         nodes.push(StatementLocation::Table(Table {
                             schema: schema.clone(),
                             name: table_name.clone(),
                             sql: sql.to_string(),
+                            inherits: Vec::new(),
+                            identity_sequences,
+                            constraints: Vec::new(),
                         }));
                     }
-                    pg_query::protobuf::AlterTableType::AtEnableRowSecurity => {
+                    pg_query::protobuf::AlterTableType::AtEnableRowSecurity
+                    | pg_query::protobuf::AlterTableType::AtDisableRowSecurity
+                    | pg_query::protobuf::AlterTableType::AtForceRowSecurity
+                    | pg_query::protobuf::AlterTableType::AtNoForceRowSecurity => {
                         nodes.push(StatementLocation::EnablePolicy(EnablePolicy {
                             schema,
                             table: table_name,
                             sql: sql.to_string(),
                         }));
                     }
+                    pg_query::protobuf::AlterTableType::AtEnableTrig
+                    | pg_query::protobuf::AlterTableType::AtEnableAlwaysTrig
+                    | pg_query::protobuf::AlterTableType::AtEnableReplicaTrig
+                    | pg_query::protobuf::AlterTableType::AtDisableTrig => {
+                        // Goes into the file of the trigger, which is created by then
+                        let names = [schema.clone(), table_name.clone(), c.name.clone()];
+                        match find_object(nodes, ObjectType::ObjectTrigger, &names, tracer) {
+                            Some(trigger) => nodes.push(trigger.with_sql(sql)),
+                            // E.g. in another input file, or an internal trigger of a constraint
+                            None => {
+                                return Err(ParseIssue::Unsupported(format!(
+                                    "enabling or disabling trigger {} which is not part of the dump",
+                                    names.join(".")
+                                )));
+                            }
+                        }
+                    }
+                    // Applies to all triggers of the table, including internal ones
+                    pg_query::protobuf::AlterTableType::AtEnableTrigAll
+                    | pg_query::protobuf::AlterTableType::AtDisableTrigAll
+                    | pg_query::protobuf::AlterTableType::AtEnableTrigUser
+                    | pg_query::protobuf::AlterTableType::AtDisableTrigUser => {
+                        nodes.push(StatementLocation::Table(Table {
+                            schema,
+                            name: table_name,
+                            sql: sql.to_string(),
+                            inherits: Vec::new(),
+                            identity_sequences: Vec::new(),
+                            constraints: Vec::new(),
+                        }));
+                    }
                     pg_query::protobuf::AlterTableType::AtAddConstraint => {
-                        if number_of_commands > 1 {
-                            let add_constraint_idx = sql.find("ADD CONSTRAINT")
-                                .expect("Expected 'ADD CONSTRAINT' in SQL");
-
-                            let commands = sql[add_constraint_idx..]
-                                .split("ADD CONSTRAINT")
-                                .collect::<Vec<_>>();
-
-                            // get from beginning to first ADD CONSTRAINT
-                            let begin = sql[sql.find("ALTER TABLE")
-                                .expect("Expected 'ALTER TABLE' in SQL")
-                                ..add_constraint_idx]
-                                .to_string();
-
-                            commands.iter().for_each(|cmd| {
-                                if cmd.is_empty() {
-                                    return;
-                                }
-
-                                let full_sql = format!(
-                                    "{}ADD CONSTRAINT{}",
-                                    begin,
-                                    cmd.trim_end().trim_end_matches(',')
-                                );
-                                parse(&full_sql, nodes);
-                            });
-                        } else if let Some(pg_query::protobuf::node::Node::Constraint(c)) =
+                        if let Some(pg_query::protobuf::node::Node::Constraint(c)) =
                             c.def.clone()
                             .expect("Missing constraint definition")
                             .node.as_ref()
@@ -413,7 +1467,7 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                                         .pktable
                                         .as_ref()
                                         .expect("Missing target table for foreign key");
-                                    let target_schema = pktable.schemaname.clone();
+                                    let target_schema = schema_or_public(&pktable.schemaname);
                                     let target_table = pktable.relname.clone();
 
                                     nodes.push(StatementLocation::ForeignKey(ForeignKey {
@@ -436,6 +1490,9 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                                         name: source_table,
                                         schema: source_schema,
                                         sql: sql.to_string(),
+                                        inherits: Vec::new(),
+                                        identity_sequences: Vec::new(),
+                                        constraints: vec![c.conname.clone()],
                                     }));
                                 }
                                 _ => {
@@ -446,29 +1503,135 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                             panic!("Missing definition for constraint");
                         }
                     }
-                    pg_query::protobuf::AlterTableType::AtChangeOwner => {} // Skip ownership changes
-                    _ => panic!("Unsupported AlterTableType: {:?} for SQL: '{}'", c.subtype(), sql),
-                },
-                _ => panic!("Unsupported command in AlterTableStmt: {:?} for SQL: '{}'", cmd, sql),
-            }
-        }
-        pg_query::NodeEnum::VariableSetStmt(n) => {
-            if n.kind() != pg_query::protobuf::VariableSetKind::VarResetAll {
-                nodes.push(StatementLocation::Setup(Setup {
-                    sql: sql.to_string(),
-                }));
-            }
-        }
-        pg_query::NodeEnum::SelectStmt(_) => {
-            nodes.push(StatementLocation::Setup(Setup {
-                sql: sql.to_string(),
+                    pg_query::protobuf::AlterTableType::AtAttachPartition
+                    | pg_query::protobuf::AlterTableType::AtDetachPartition => {
+                        let partition = match c.def.as_ref().and_then(|d| d.node.as_ref()) {
+                            Some(NodeEnum::PartitionCmd(p)) => {
+                                p.name.as_ref().expect("Missing partition in PartitionCmd")
+                            }
+                            other => panic!("Expected PartitionCmd, found {:?}", other),
+                        };
+
+                        if n.objtype() == ObjectType::ObjectIndex {
+                            // `ALTER INDEX parent_index ATTACH PARTITION partition_index` goes
+                            // into the file of the partition's index
+                            let schema = schema_or_public(&partition.schemaname);
+                            let name = partition.relname.clone();
+
+                            match index_location(nodes, &schema, &name, sql) {
+                                Some(index) => nodes.push(index),
+                                None => {
+                                    return Err(ParseIssue::Unsupported(format!(
+                                        "attaching index {}.{} which is not part of the dump",
+                                        schema, name
+                                    )));
+                                }
+                            }
+                        } else {
+                            nodes.push(StatementLocation::Partition(Partition {
+                                schema: schema_or_public(&partition.schemaname),
+                                name: partition.relname.clone(),
+                                parent_schema: schema,
+                                parent: table_name,
+                                sql: sql.to_string(),
+                            }));
+                        }
+                    }
+                    pg_query::protobuf::AlterTableType::AtChangeOwner => {
+                        // Owners are skipped unless asked for, the role is kept as written in the
+                        // dump so quoted role names stay quoted
+                        if !options.keep_owners {
+                            return Err(ParseIssue::Skipped("ownership change"));
+                        }
+
+                        // pg_dump changes the owner of views and sequences with `ALTER TABLE` too
+                        let objtype = match n.objtype() {
+                            ObjectType::ObjectTable if find_view(nodes, &schema, &table_name, tracer) => {
+                                ObjectType::ObjectView
+                            }
+                            ObjectType::ObjectTable if find_sequence(nodes, &schema, &table_name, tracer) => {
+                                ObjectType::ObjectSequence
+                            }
+                            objtype => objtype,
+                        };
+
+                        match objtype {
+                            ObjectType::ObjectTable => nodes.push(StatementLocation::Table(Table {
+                                schema,
+                                name: table_name,
+                                sql: sql.to_string(),
+                                inherits: Vec::new(),
+                                identity_sequences: Vec::new(),
+                                constraints: Vec::new(),
+                            })),
+                            ObjectType::ObjectView => nodes.push(StatementLocation::View(View {
+                                schema,
+                                name: table_name,
+                                sql: sql.to_string(),
+                            })),
+                            // Like every other statement of a sequence, the owner goes into the
+                            // file of the table that owns the sequence
+                            ObjectType::ObjectSequence => {
+                                let owner = sequence_owner(nodes, &schema, &table_name);
+                                nodes.push(StatementLocation::Sequence(Sequence {
+                                    table: owner.as_ref().map(|(_, table)| table.clone()),
+                                    table_schema: owner.map(|(schema, _)| schema),
+                                    schema,
+                                    name: table_name,
+                                    sql: sql.to_string(),
+                                }));
+                            }
+                            other => {
+                                return Err(ParseIssue::Unsupported(format!("ownership change of {:?}", other)));
+                            }
+                        }
+                    }
+                    _ => {
+                        return Err(ParseIssue::Unsupported(format!("AlterTableType {:?}", c.subtype())));
+                    }
+                },
+                other => {
+                    return Err(ParseIssue::Unsupported(format!(
+                        "{} in AlterTableStmt",
+                        node_kind(other)
+                    )));
+                }
+            }
+        }
+        pg_query::NodeEnum::VariableSetStmt(n) => {
+            if n.kind() == pg_query::protobuf::VariableSetKind::VarResetAll {
+                return Err(ParseIssue::Skipped("RESET ALL"));
+            }
+
+            // Transactions of the dump are not written, so `SET LOCAL` and `SET TRANSACTION` would
+            // only raise a warning. `SET SESSION` is a plain `SET` and is kept like one.
+            if n.is_local || n.name.eq_ignore_ascii_case("transaction") {
+                return Err(ParseIssue::Skipped("setting outside a transaction"));
+            }
+
+            // Session settings of the dump like `statement_timeout` are left out if asked for, also
+            // when they are reset
+            if options
+                .skip_settings
+                .iter()
+                .any(|setting| setting.eq_ignore_ascii_case(&n.name))
+            {
+                return Err(ParseIssue::Skipped("session setting"));
+            }
+
+            nodes.push(StatementLocation::Setup(Setup {
+                sql: sql.to_string(),
+            }));
+        }
+        pg_query::NodeEnum::SelectStmt(_) => {
+            nodes.push(StatementLocation::Setup(Setup {
+                sql: sql.to_string(),
             }));
         }
         pg_query::NodeEnum::AlterOwnerStmt(n) => match n.object_type() {
             pg_query::protobuf::ObjectType::ObjectSchema => {
-                let schema_name = get_sval(&n.object
-                    .expect("Missing object in AlterOwnerStmt")
-                    .node);
+                let schema_name =
+                    get_sval(&n.object.expect("Missing object in AlterOwnerStmt").node);
 
                 nodes.push(StatementLocation::Schema(Schema {
                     name: schema_name,
@@ -559,7 +1722,7 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                     let function_name = items.last()
                         .expect("Missing function name in owner");
 
-                    if find_trigger_function(nodes, schema, function_name) {
+                    if find_trigger_function(nodes, schema, function_name, tracer) {
                         nodes.push(StatementLocation::TriggerFunction(TriggerFunction {
                             name: function_name.to_string(),
                             schema: schema.to_string(),
@@ -597,25 +1760,13 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                     let type_name = items.get(1)
                         .expect("Missing type name in owner");
 
-                    if nodes.iter().any(|n| {
-                        if let StatementLocation::EnumNode(e) = n {
-                            e.name == *type_name && e.schema == *schema
-                        } else {
-                            false
-                        }
-                    }) {
+                    if find_enum(nodes, schema, type_name, tracer) {
                         nodes.push(StatementLocation::EnumNode(Enum {
                             name: type_name.to_string(),
                             schema: schema.to_string(),
                             sql: sql.to_string(),
                         }));
-                    } else if nodes.iter().any(|n| {
-                        if let StatementLocation::CompositeType(t) = n {
-                            t.name == *type_name && t.schema == *schema
-                        } else {
-                            false
-                        }
-                    }) {
+                    } else if find_composite_type(nodes, schema, type_name, tracer) {
                         nodes.push(StatementLocation::CompositeType(CompositeType {
                             name: type_name.to_string(),
                             schema: schema.to_string(),
@@ -631,17 +1782,59 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                     panic!("Expected List for type owner");
                 }
             }
+            pg_query::protobuf::ObjectType::ObjectCollation => {
+                if let pg_query::NodeEnum::List(l) = n
+                    .object
+                    .expect("Missing object in AlterOwnerStmt")
+                    .node
+                    .expect("Missing node in AlterOwnerStmt object")
+                {
+                    let items = extract_names(&l.items, "collation owner");
+                    let (schema, collation_name) =
+                        extract_schema_and_name(&items, "collation owner");
+
+                    nodes.push(StatementLocation::Collation(Collation {
+                        name: collation_name.to_string(),
+                        schema: schema.to_string(),
+                        sql: sql.to_string(),
+                    }));
+                } else {
+                    panic!("Expected List for collation owner");
+                }
+            }
+            pg_query::protobuf::ObjectType::ObjectForeignServer => {
+                let server_name =
+                    get_sval(&n.object.expect("Missing object in AlterOwnerStmt").node);
+
+                nodes.push(StatementLocation::ForeignServer(ForeignServer {
+                    name: server_name,
+                    sql: sql.to_string(),
+                }));
+            }
+            kind @ (pg_query::protobuf::ObjectType::ObjectTsconfiguration
+            | pg_query::protobuf::ObjectType::ObjectTsdictionary) => {
+                if let Some(NodeEnum::List(l)) = n.object.as_ref().and_then(|o| o.node.as_ref()) {
+                    let names = extract_names(&l.items, "text search owner");
+                    nodes.push(text_search(&names, kind, sql));
+                } else {
+                    panic!("Expected List for text search owner");
+                }
+            }
             _ => {
-                panic!("Unsupported object type in AlterOwnerStmt: {:?}", n.object_type());
+                return Err(ParseIssue::Unsupported(format!(
+                    "object type in AlterOwnerStmt {:?}",
+                    n.object_type()
+                )));
             }
         },
         pg_query::NodeEnum::CreateSeqStmt(n) => {
             let range_var = n.sequence.expect("Missing sequence in CreateSeqStmt");
-            let schema_name = range_var.schemaname;
+            let schema_name = schema_or_public(&range_var.schemaname);
             let rel_name = range_var.relname;
 
             nodes.push(StatementLocation::Sequence(Sequence {
                 table: None,
+                table_schema: None,
                 name: rel_name.to_string(),
                 schema: schema_name.to_string(),
                 sql: sql.to_string(),
@@ -649,62 +1842,70 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
         }
         pg_query::NodeEnum::AlterSeqStmt(n) => {
             let range_var = n.sequence.expect("Missing sequence in AlterSeqStmt");
-            let schema_name = range_var.schemaname;
+            let schema_name = schema_or_public(&range_var.schemaname);
             let rel_name = range_var.relname;
 
-            let opts = n
-                .options
-                .iter()
-                .find_map(|o| {
-                    if let pg_query::NodeEnum::DefElem(d) = &o.node
-                        .clone()
-                        .expect("Missing node in AlterSeqStmt option")
-                    {
-                        if d.defname == "owned_by" {
-                            Some(d.clone())
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .expect("Only owned_by is supported in AlterSeqStmt");
+            // Only `OWNED BY` decides the placement, other options like `RESTART` go wherever the
+            // sequence is written
+            let owned_by = n.options.iter().find_map(|o| match &o.node {
+                Some(pg_query::NodeEnum::DefElem(d)) if d.defname == "owned_by" => Some(d.clone()),
+                _ => None,
+            });
 
-            if let pg_query::NodeEnum::List(l) = opts.arg
-                .expect("Missing arg in owned_by option")
-                .node
-                .expect("Missing node in owned_by option")
-            {
-                let items = l
-                    .items
-                    .iter()
-                    .map(|n| get_sval(&n.node))
-                    .collect::<Vec<_>>();
-
-                if items.len() != 3 {
-                    panic!("Expected 3 items in sequence owned_by list, found {}", items.len());
+            let owner = owned_by.map(|opts| {
+                if let pg_query::NodeEnum::List(l) = opts
+                    .arg
+                    .expect("Missing arg in owned_by option")
+                    .node
+                    .expect("Missing node in owned_by option")
+                {
+                    l.items
+                        .iter()
+                        .map(|n| get_sval(&n.node))
+                        .collect::<Vec<_>>()
+                } else {
+                    panic!("Expected List for sequence owned_by");
                 }
+            });
 
-                let schema = items.first()
-                    .expect("Missing schema in sequence owned_by");
-                if *schema != schema_name {
-                    panic!("Schema name mismatch in sequence owned_by: {} != {}", schema, schema_name);
+            let (table_schema, table) = match owner.as_deref() {
+                // The owning table may live in a different schema than the sequence
+                Some([table_schema, table_name, _column]) => {
+                    (Some(table_schema.clone()), Some(table_name.clone()))
                 }
-                let table_name = items.get(1)
-                    .expect("Missing table name in sequence owned_by");
+                Some([table_name, _column]) => {
+                    (Some("public".to_string()), Some(table_name.clone()))
+                }
+                // `OWNED BY NONE`
+                Some([_]) => (None, None),
+                None => match sequence_owner(nodes, &schema_name, &rel_name) {
+                    Some((table_schema, table)) => (Some(table_schema), Some(table)),
+                    None => (None, None),
+                },
+                Some(items) => panic!(
+                    "Expected 2 or 3 items in sequence owned_by list, found {}",
+                    items.len()
+                ),
+            };
 
-                nodes.push(StatementLocation::Sequence(Sequence {
-                    table: Some(table_name.clone()),
-                    name: rel_name.to_string(),
-                    schema: schema_name.to_string(),
-                    sql: sql.to_string(),
-                }));
-            } else {
-                panic!("Expected List for sequence owned_by");
-            }
+            nodes.push(StatementLocation::Sequence(Sequence {
+                table,
+                table_schema,
+                name: rel_name.to_string(),
+                schema: schema_name.to_string(),
+                sql: sql.to_string(),
+            }));
         }
         pg_query::NodeEnum::GrantStmt(n) => {
+            // Each object of a grant may live in a different file, so we split the grant into one
+            // statement per object
+            if n.objects.len() > 1 {
+                split_grant(&n).iter().for_each(|grant_sql| {
+                    parse(grant_sql, nodes, options, tracer, issues);
+                });
+                return Ok(());
+            }
+
             match n.objtype() {
                 pg_query::protobuf::ObjectType::ObjectSchema => {
                     let schema_name = get_sval(&n.objects.first()
@@ -724,28 +1925,53 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                         .expect("Missing node in table grant object");
 
                     if let pg_query::NodeEnum::RangeVar(obj) = range_var {
-                        let schema = obj.schemaname.clone();
+                        let schema = schema_or_public(&obj.schemaname);
                         let name = obj.relname.clone();
 
-                        if find_table(nodes, &schema, &name) {
+                        if find_table(nodes, &schema, &name, tracer) {
                             nodes.push(StatementLocation::Table(Table {
                                 schema,
                                 name,
                                 sql: sql.to_string(),
+                                inherits: Vec::new(),
+                                identity_sequences: Vec::new(),
+                                constraints: Vec::new(),
                             }));
-                        } else if find_view(nodes, &schema, &name) {
+                        } else if find_view(nodes, &schema, &name, tracer) {
                             nodes.push(StatementLocation::View(View {
                                 schema,
                                 name,
                                 sql: sql.to_string(),
                             }));
+                        } else if find_foreign_table(nodes, &schema, &name, tracer) {
+                            nodes.push(StatementLocation::ForeignTable(ForeignTable {
+                                schema,
+                                name,
+                                sql: sql.to_string(),
+                            }));
                         } else {
-                            panic!("No table or view found for {}.{}", schema, name);
+                            return Err(ParseIssue::Unsupported(format!(
+                                "grant on table {}.{} which is not part of the dump",
+                                schema, name
+                            )));
                         }
                     } else {
                         panic!("Expected RangeVar for table grant, found {:?}", range_var);
                     }
                 }
+                pg_query::protobuf::ObjectType::ObjectForeignServer => {
+                    let server_name = get_sval(
+                        &n.objects
+                            .first()
+                            .expect("Missing object in server grant")
+                            .node,
+                    );
+
+                    nodes.push(StatementLocation::ForeignServer(ForeignServer {
+                        name: server_name,
+                        sql: sql.to_string(),
+                    }));
+                }
                 pg_query::protobuf::ObjectType::ObjectSequence => {
                     let range_var = &n.objects.first()
                         .expect("Missing object in sequence grant")
@@ -754,9 +1980,15 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                         .expect("Missing node in sequence grant object");
 
                     if let pg_query::NodeEnum::RangeVar(obj) = range_var {
+                        // The grant goes into the file of the table that owns the sequence, like
+                        // every other statement of the sequence
+                        let owner =
+                            sequence_owner(nodes, &schema_or_public(&obj.schemaname), &obj.relname);
+
                         nodes.push(StatementLocation::Sequence(Sequence {
-                            table: None,
-                            schema: obj.schemaname.clone(),
+                            table: owner.as_ref().map(|(_, table)| table.clone()),
+                            table_schema: owner.map(|(schema, _)| schema),
+                            schema: schema_or_public(&obj.schemaname),
                             name: obj.relname.clone(),
                             sql: sql.to_string(),
                         }));
@@ -787,87 +2019,1158 @@ fn parse(sql: &str, nodes: &mut Vec<StatementLocation>) {
                         let function_name = items.last()
                             .expect("Missing function name in function grant");
 
-                        if find_trigger_function(nodes, schema, function_name) {
+                        if find_trigger_function(nodes, schema, function_name, tracer) {
                             nodes.push(StatementLocation::TriggerFunction(TriggerFunction {
                                 name: function_name.to_string(),
                                 schema: schema.to_string(),
                                 sql: sql.to_string(),
                             }));
-                        } else if find_function(nodes, schema, function_name) {
+                        } else if find_function(nodes, schema, function_name, tracer) {
                             nodes.push(StatementLocation::Function(Function {
                                 name: function_name.to_string(),
                                 schema: schema.to_string(),
                                 sql: sql.to_string(),
                             }));
-                        } else if find_aggregate(nodes, schema, function_name) {
+                        } else if find_aggregate(nodes, schema, function_name, tracer) {
                             nodes.push(StatementLocation::Aggregate(Aggregate {
                                 name: function_name.to_string(),
                                 schema: schema.to_string(),
                                 sql: sql.to_string(),
                             }));
                         } else {
-                            panic!("No trigger or function or aggregate found for {}.{}", schema, function_name);
+                            return Err(ParseIssue::Unsupported(format!(
+                                "grant on function {}.{} which is not part of the dump",
+                                schema, function_name
+                            )));
                         }
                     } else {
                         panic!("Expected ObjectWithArgs for function grant, found {:?}", list);
                     }
                 }
                 _ => {
-                    panic!("Unsupported object type in GrantStmt: {:?}", n.objtype());
+                    return Err(ParseIssue::Unsupported(format!(
+                        "object type in GrantStmt {:?}",
+                        n.objtype()
+                    )));
                 }
             };
         }
         pg_query::NodeEnum::AlterDefaultPrivilegesStmt(n) => {
-            // Extract schema from options
-            let schema_elem = n
+            match default_privileges_schemas(&n).as_slice() {
+                // Without `IN SCHEMA`, the privileges apply to objects created in any schema
+                [] => {
+                    nodes.push(StatementLocation::DefaultPrivileges(DefaultPrivileges {
+                        sql: sql.to_string(),
+                    }));
+                }
+                [schema_name] => {
+                    nodes.push(StatementLocation::Schema(Schema {
+                        name: schema_name.clone(),
+                        sql: sql.to_string(),
+                    }));
+                }
+                // Each schema has its own index, so we split the statement into one per schema
+                _ => {
+                    split_default_privileges(&n)
+                        .iter()
+                        .for_each(|privileges_sql| {
+                            parse(privileges_sql, nodes, options, tracer, issues);
+                        });
+                }
+            }
+        }
+        NodeEnum::CreateExtensionStmt(n) => {
+            // `pg_dump` always names the schema, without it the extension is created in the first
+            // schema of the search path
+            let schema = n
                 .options
                 .iter()
-                .find_map(|o| {
-                    if let pg_query::NodeEnum::DefElem(d) = &o.node
-                        .clone()
-                        .expect("Missing node in AlterDefaultPrivilegesStmt option")
-                    {
-                        if d.defname == "schemas" {
-                            Some(d.clone())
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
+                .find_map(|o| match &o.node {
+                    Some(NodeEnum::DefElem(d)) if d.defname == "schema" => {
+                        Some(get_sval(&d.arg.as_ref()?.node))
                     }
+                    _ => None,
                 })
-                .expect("schemas option is required in AlterDefaultPrivilegesStmt");
+                .unwrap_or_else(|| "public".to_string());
 
-            // Extract schema name from the list
-            let schema_name = if let pg_query::NodeEnum::List(l) =
-                schema_elem.arg
-                    .expect("Missing arg in schema option")
-                    .node
-                    .expect("Missing node in schema option arg")
-            {
-                if let Some(item) = l.items.first() {
-                    if let pg_query::NodeEnum::String(s) = &item.node
-                        .clone()
-                        .expect("Missing node in schema list item")
-                    {
-                        s.sval.clone()
-                    } else {
-                        panic!("Expected String in schema list, found {:?}", item.node);
+            nodes.push(StatementLocation::Extension(Extension {
+                schema,
+                name: n.extname,
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::CreateForeignServerStmt(n) => {
+            nodes.push(StatementLocation::ForeignServer(ForeignServer {
+                name: n.servername,
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::AlterForeignServerStmt(n) => {
+            nodes.push(StatementLocation::ForeignServer(ForeignServer {
+                name: n.servername,
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::CreateUserMappingStmt(n) => {
+            nodes.push(StatementLocation::UserMapping(UserMapping {
+                server: n.servername,
+                user: role_name(
+                    n.user
+                        .as_ref()
+                        .expect("Missing user in CreateUserMappingStmt"),
+                ),
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::AlterUserMappingStmt(n) => {
+            nodes.push(StatementLocation::UserMapping(UserMapping {
+                server: n.servername,
+                user: role_name(
+                    n.user
+                        .as_ref()
+                        .expect("Missing user in AlterUserMappingStmt"),
+                ),
+                sql: sql.to_string(),
+            }));
+        }
+        // Roles belong to the cluster rather than the schema, so they are only written if asked for
+        NodeEnum::CreateRoleStmt(_)
+        | NodeEnum::AlterRoleStmt(_)
+        | NodeEnum::AlterRoleSetStmt(_)
+        | NodeEnum::GrantRoleStmt(_)
+            if !options.include_roles =>
+        {
+            return Err(ParseIssue::Skipped("role"));
+        }
+        NodeEnum::CreateRoleStmt(n) => {
+            nodes.push(StatementLocation::Role(Role {
+                name: n.role,
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::AlterRoleStmt(n) => {
+            nodes.push(StatementLocation::Role(Role {
+                name: role_name(n.role.as_ref().expect("Missing role in AlterRoleStmt")),
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::AlterRoleSetStmt(n) => {
+            // `ALTER ROLE ALL SET ...` applies to every role
+            let Some(role) = n.role.as_ref() else {
+                return Err(ParseIssue::Unsupported("ALTER ROLE ALL".to_string()));
+            };
+
+            nodes.push(StatementLocation::Role(Role {
+                name: role_name(role),
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::GrantRoleStmt(n) => {
+            // A membership goes into the file of the member, e.g. `GRANT anon TO authenticator`
+            // into the file of `authenticator`
+            let member = match n.grantee_roles.first().and_then(|r| r.node.as_ref()) {
+                Some(NodeEnum::RoleSpec(r)) => role_name(r),
+                other => panic!(
+                    "Expected RoleSpec as grantee in GrantRoleStmt, found {:?}",
+                    other
+                ),
+            };
+
+            nodes.push(StatementLocation::Role(Role {
+                name: member,
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::CreateForeignTableStmt(n) => {
+            let rel = n
+                .base_stmt
+                .and_then(|s| s.relation)
+                .expect("Missing relation in CreateForeignTableStmt");
+
+            nodes.push(StatementLocation::ForeignTable(ForeignTable {
+                schema: schema_or_public(&rel.schemaname),
+                name: rel.relname,
+                sql: sql.to_string(),
+            }));
+        }
+        NodeEnum::DropStmt(n) => {
+            // Each object may live in a different file, so we split the drop into one statement
+            // per object
+            if n.objects.len() > 1 {
+                split_drop(&n).iter().for_each(|drop_sql| {
+                    parse(drop_sql, nodes, options, tracer, issues);
+                });
+                return Ok(());
+            }
+
+            let names = object_names(n.objects.first().expect("Missing object in DropStmt"));
+
+            // A drop is written next to the statements of the object it drops, so that a drop and
+            // recreate stays in order
+            let node = match find_object(nodes, n.remove_type(), &names, tracer) {
+                Some(dropped) => dropped.in_dump_order(sql),
+                None => StatementLocation::Drop(DropStatement {
+                    sql: sql.to_string(),
+                }),
+            };
+            nodes.push(node);
+        }
+        NodeEnum::AlterObjectSchemaStmt(n) => match n.object_type() {
+            kind @ (ObjectType::ObjectAggregate
+            | ObjectType::ObjectFunction
+            | ObjectType::ObjectProcedure
+            | ObjectType::ObjectType) => {
+                // The statement has to run after the object is created, so it goes into the file
+                // of the old schema together with the definition
+                let names = object_names(
+                    n.object
+                        .as_ref()
+                        .expect("Missing object in AlterObjectSchemaStmt"),
+                );
+                match find_object(nodes, kind, &names, tracer) {
+                    Some(object) => nodes.push(object.with_sql(sql)),
+                    None => {
+                        return Err(ParseIssue::Unsupported(format!(
+                            "SET SCHEMA of {} which is not part of the dump",
+                            names.join(".")
+                        )));
                     }
+                }
+            }
+            other => {
+                return Err(ParseIssue::Unsupported(format!(
+                    "object type in AlterObjectSchemaStmt {:?}",
+                    other
+                )));
+            }
+        },
+        NodeEnum::RenameStmt(n) => {
+            // Later statements use the new name, so the object is written to the file of the new
+            // name. The statements so far are moved along, so that the object keeps one file.
+            let relation = n
+                .relation
+                .as_ref()
+                .map(|r| (schema_or_public(&r.schemaname), r.relname.clone()));
+            let (kind, names) = match (n.rename_type(), &relation) {
+                (
+                    kind @ (ObjectType::ObjectTable
+                    | ObjectType::ObjectView
+                    | ObjectType::ObjectMatview
+                    | ObjectType::ObjectSequence
+                    | ObjectType::ObjectForeignTable
+                    | ObjectType::ObjectIndex),
+                    Some((schema, name)),
+                ) => {
+                    rename_object(nodes, kind, schema, None, name, &n.newname);
+                    (kind, vec![schema.clone(), n.newname.clone()])
+                }
+                (
+                    kind @ (ObjectType::ObjectTrigger | ObjectType::ObjectPolicy),
+                    Some((schema, table)),
+                ) => {
+                    rename_object(nodes, kind, schema, Some(table), &n.subname, &n.newname);
+                    (kind, vec![schema.clone(), table.clone(), n.newname.clone()])
+                }
+                // A foreign key has a file of its own, other constraints are part of the table
+                (ObjectType::ObjectTabconstraint, Some((schema, table)))
+                    if nodes.iter().any(|node| matches!(node, StatementLocation::ForeignKey(fk)
+                        if fk.source_schema == *schema && fk.source_table == *table && fk.constraint_name == n.subname)) =>
+                {
+                    rename_object(nodes, ObjectType::ObjectTabconstraint, schema, Some(table), &n.subname, &n.newname);
+                    let fk = nodes
+                        .iter()
+                        .find(|node| matches!(node, StatementLocation::ForeignKey(fk)
+                            if fk.source_schema == *schema && fk.source_table == *table && fk.constraint_name == n.newname))
+                        .expect("Missing renamed foreign key");
+                    nodes.push(fk.in_dump_order(sql));
+                    return Ok(());
+                }
+                (ObjectType::ObjectColumn, Some((schema, table))) => {
+                    (n.relation_type(), vec![schema.clone(), table.clone()])
+                }
+                // Only the rename of a column names the kind of its relation
+                (ObjectType::ObjectTabconstraint, Some((schema, table))) => {
+                    (ObjectType::ObjectTable, vec![schema.clone(), table.clone()])
+                }
+                // The attribute of a composite type
+                (ObjectType::ObjectAttribute, Some((schema, name))) => {
+                    (ObjectType::ObjectType, vec![schema.clone(), name.clone()])
+                }
+                (
+                    kind @ (ObjectType::ObjectFunction
+                    | ObjectType::ObjectProcedure
+                    | ObjectType::ObjectAggregate
+                    | ObjectType::ObjectType),
+                    _,
+                ) => {
+                    let names = object_names(n.object.as_ref().expect("Missing object in RenameStmt"));
+                    let (schema, name) = extract_schema_and_name(&names, "renamed object");
+                    let schema = schema.to_string();
+                    rename_object(nodes, kind, &schema, None, name, &n.newname);
+                    (kind, vec![schema, n.newname.clone()])
+                }
+                (kind, _) => return Err(ParseIssue::Unsupported(format!("rename of {:?}", kind))),
+            };
+
+            // `ALTER TABLE` works on views and sequences too, and the index of a constraint is
+            // part of its table
+            let object = find_object(nodes, kind, &names, tracer)
+                .cloned()
+                .or_else(|| match (kind, &relation) {
+                    (ObjectType::ObjectTable, _) => [
+                        ObjectType::ObjectView,
+                        ObjectType::ObjectSequence,
+                        ObjectType::ObjectForeignTable,
+                    ]
+                    .into_iter()
+                    .find_map(|kind| find_object(nodes, kind, &names, tracer).cloned()),
+                    (ObjectType::ObjectIndex, Some((schema, name))) => {
+                        find_constraint_table(nodes, schema, name).map(|table| {
+                            StatementLocation::Table(Table {
+                                schema: schema.clone(),
+                                name: table,
+                                sql: String::new(),
+                                inherits: Vec::new(),
+                                identity_sequences: Vec::new(),
+                                constraints: Vec::new(),
+                            })
+                        })
+                    }
+                    _ => None,
+                });
+            match object {
+                Some(object) => nodes.push(object.in_dump_order(sql)),
+                None => {
+                    return Err(ParseIssue::Unsupported(format!(
+                        "rename of {} which is not part of the dump",
+                        names.join(".")
+                    )));
+                }
+            }
+        }
+        NodeEnum::AlterTsconfigurationStmt(n) => {
+            // Mappings are added after the configuration is created, so they go into its file
+            let names = extract_names(&n.cfgname, "text search configuration");
+            nodes.push(text_search(&names, ObjectType::ObjectTsconfiguration, sql));
+        }
+        NodeEnum::AlterTsdictionaryStmt(n) => {
+            let names = extract_names(&n.dictname, "text search dictionary");
+            nodes.push(text_search(&names, ObjectType::ObjectTsdictionary, sql));
+        }
+        _ => return Err(ParseIssue::Unsupported(node_kind(&node))),
+    }
+
+    Ok(())
+}
+
+/// The text of a reconstructed comment. The parser returns an empty string for `IS NULL`, which
+/// removes a comment just like `IS ''` does, so both are written as `NULL`.
+fn comment_text(comment: &str) -> String {
+    if comment.is_empty() {
+        "NULL".to_string()
+    } else {
+        string_literal(comment)
+    }
+}
+
+/// Quote a string as a SQL literal. Text with backslashes or line breaks is dollar-quoted, so it
+/// is written verbatim and can't be reinterpreted as escape sequences when replayed.
+fn string_literal(text: &str) -> String {
+    if !text.contains(['\\', '\n', '\r']) {
+        return format!("'{}'", text.replace('\'', "''"));
+    }
+
+    // The tag must not end the literal early, also not if the text ends with a part of it
+    let mut tag = String::new();
+    while format!("{}${}$", text, tag).find(&format!("${}$", tag)) != Some(text.len()) {
+        tag.push('_');
+    }
+    format!("${}${}${}$", tag, text, tag)
+}
+
+/// Join the parts of a qualified name, quoting each part according to the quote style
+fn quote_qualified(parts: &[&str], style: QuoteStyle) -> String {
+    parts
+        .iter()
+        .map(|part| match style {
+            QuoteStyle::Always => quote_ident(part),
+            QuoteStyle::Minimal if needs_quotes(part) => quote_ident(part),
+            QuoteStyle::Minimal => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Whether an identifier has to be quoted, following the rules of Postgres' `quote_ident`: it
+/// must consist of lowercase letters, digits and underscores only and must not be a keyword
+/// other than an unreserved one
+fn needs_quotes(ident: &str) -> bool {
+    let is_simple = ident
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && ident
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    if !is_simple {
+        return true;
+    }
+
+    pg_query::scan(ident)
+        .expect("Failed to scan identifier")
+        .tokens
+        .first()
+        .is_some_and(|t| {
+            !matches!(
+                t.keyword_kind(),
+                KeywordKind::NoKeyword | KeywordKind::UnreservedKeyword
+            )
+        })
+}
+
+/// Find the sequence used by a `nextval('...')` call in a column default or definition
+fn nextval_sequence(node: &Node) -> Option<(String, String)> {
+    match node.node.as_ref()? {
+        NodeEnum::FuncCall(f) => {
+            let names = extract_names(&f.funcname, "function call");
+            if names.last().is_some_and(|n| n == "nextval") {
+                let arg = f.args.first()?;
+                let literal = match arg.node.as_ref()? {
+                    NodeEnum::TypeCast(t) => t.arg.as_deref()?,
+                    _ => arg,
+                };
+
+                if let Some(NodeEnum::AConst(c)) = &literal.node
+                    && let Some(pg_query::protobuf::a_const::Val::Sval(s)) = &c.val
+                {
+                    return match split_qualified_name(&s.sval).as_slice() {
+                        [name] => Some(("public".to_string(), name.clone())),
+                        [.., schema, name] => Some((schema.clone(), name.clone())),
+                        [] => None,
+                    };
+                }
+            }
+            None
+        }
+        NodeEnum::TypeCast(t) => nextval_sequence(t.arg.as_deref()?),
+        NodeEnum::ColumnDef(d) => d.constraints.iter().find_map(nextval_sequence),
+        NodeEnum::Constraint(c) => nextval_sequence(c.raw_expr.as_deref()?),
+        _ => None,
+    }
+}
+
+/// Split a possibly quoted, qualified name like `"api".my_seq` into its parts
+fn split_qualified_name(name: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                part.push('"');
+                chars.next();
+            }
+            '"' => {
+                quoted = !quoted;
+                was_quoted = true;
+            }
+            '.' if !quoted => {
+                parts.push(if was_quoted {
+                    part
                 } else {
-                    panic!("Empty schema list in AlterDefaultPrivilegesStmt");
+                    part.to_lowercase()
+                });
+                part = String::new();
+                was_quoted = false;
+            }
+            c => part.push(c),
+        }
+    }
+    parts.push(if was_quoted {
+        part
+    } else {
+        part.to_lowercase()
+    });
+
+    parts
+}
+
+/// Assign a sequence that is not owned by any table to the given table. A sequence in another
+/// schema keeps its own file, so that `--schemas` keeps or leaves it out with its schema, and is
+/// ordered before the table by the order of the schemas.
+fn adopt_sequence(
+    nodes: &mut [StatementLocation],
+    seq_schema: &str,
+    seq_name: &str,
+    table_schema: &str,
+    table: &str,
+) {
+    if seq_schema != table_schema {
+        return;
+    }
+
+    let is_owned = nodes.iter().any(|n| {
+        matches!(n, StatementLocation::Sequence(s)
+            if s.schema == seq_schema && s.name == seq_name && s.table.is_some())
+    });
+
+    if is_owned {
+        return;
+    }
+
+    nodes.iter_mut().for_each(|n| {
+        if let StatementLocation::Sequence(s) = n
+            && s.schema == seq_schema
+            && s.name == seq_name
+        {
+            s.table = Some(table.to_string());
+            s.table_schema = Some(table_schema.to_string());
+        }
+    });
+}
+
+/// Rename an object in the statements so far, including the references of the statements that
+/// belong to it, e.g. the table of its indexes or the function of its triggers, so that all of
+/// them are written to the file of the new name. `table` is the table of a trigger, policy or
+/// constraint.
+fn rename_object(
+    nodes: &mut [StatementLocation],
+    kind: ObjectType,
+    schema: &str,
+    table: Option<&str>,
+    old: &str,
+    new: &str,
+) {
+    let rename = |object_schema: &str, name: &mut String| {
+        if object_schema == schema && name == old {
+            *name = new.to_string();
+        }
+    };
+    let on_table = |object_schema: &str, object_table: &str| {
+        object_schema == schema && Some(object_table) == table
+    };
+
+    let objects = nodes.iter_mut().map(|n| match n {
+        StatementLocation::DumpOrdered(n) => n.object.as_mut(),
+        n => n,
+    });
+    objects.for_each(|n| match (kind, n) {
+        (ObjectType::ObjectIndex, StatementLocation::Index(i)) => rename(&i.schema, &mut i.name),
+        (ObjectType::ObjectIndex, _) => {}
+        (ObjectType::ObjectTrigger, StatementLocation::Trigger(t))
+            if on_table(&t.schema, &t.table) =>
+        {
+            rename(&t.schema, &mut t.name)
+        }
+        (ObjectType::ObjectPolicy, StatementLocation::Policy(p))
+            if on_table(&p.schema, &p.table) =>
+        {
+            rename(&p.schema, &mut p.name)
+        }
+        (ObjectType::ObjectTabconstraint, StatementLocation::ForeignKey(fk))
+            if on_table(&fk.source_schema, &fk.source_table) =>
+        {
+            rename(&fk.source_schema, &mut fk.constraint_name)
+        }
+        (
+            ObjectType::ObjectTrigger | ObjectType::ObjectPolicy | ObjectType::ObjectTabconstraint,
+            _,
+        ) => {}
+        (
+            ObjectType::ObjectFunction | ObjectType::ObjectProcedure,
+            StatementLocation::Function(f),
+        ) => rename(&f.schema, &mut f.name),
+        (
+            ObjectType::ObjectFunction | ObjectType::ObjectProcedure,
+            StatementLocation::TriggerFunction(f),
+        ) => rename(&f.schema, &mut f.name),
+        (
+            ObjectType::ObjectFunction | ObjectType::ObjectProcedure,
+            StatementLocation::Trigger(t),
+        ) => rename(&t.function_schema, &mut t.function),
+        (
+            ObjectType::ObjectFunction | ObjectType::ObjectProcedure,
+            StatementLocation::Policy(p),
+        ) => p
+            .functions
+            .iter_mut()
+            .for_each(|(function_schema, function)| rename(function_schema, function)),
+        (ObjectType::ObjectFunction | ObjectType::ObjectProcedure, _) => {}
+        (ObjectType::ObjectAggregate, StatementLocation::Aggregate(a)) => {
+            rename(&a.schema, &mut a.name)
+        }
+        (ObjectType::ObjectAggregate, _) => {}
+        (ObjectType::ObjectType, StatementLocation::EnumNode(e)) => rename(&e.schema, &mut e.name),
+        (ObjectType::ObjectType, StatementLocation::CompositeType(c)) => {
+            rename(&c.schema, &mut c.name)
+        }
+        (ObjectType::ObjectType, _) => {}
+        // A relation, which the statements of its indexes, policies and the like refer to
+        (_, StatementLocation::Table(t)) => rename(&t.schema, &mut t.name),
+        (_, StatementLocation::View(v)) => rename(&v.schema, &mut v.name),
+        (_, StatementLocation::ForeignTable(t)) => rename(&t.schema, &mut t.name),
+        (_, StatementLocation::Partition(p)) => {
+            rename(&p.schema, &mut p.name);
+            rename(&p.parent_schema, &mut p.parent);
+        }
+        (_, StatementLocation::Sequence(s)) => {
+            rename(&s.schema, &mut s.name);
+            let table_schema = s.table_schema.clone().unwrap_or_else(|| s.schema.clone());
+            if let Some(table) = &mut s.table {
+                rename(&table_schema, table);
+            }
+        }
+        (_, StatementLocation::Index(i)) => rename(&i.schema, &mut i.table),
+        (_, StatementLocation::Policy(p)) => rename(&p.schema, &mut p.table),
+        (_, StatementLocation::EnablePolicy(p)) => rename(&p.schema, &mut p.table),
+        (_, StatementLocation::Trigger(t)) => rename(&t.schema, &mut t.table),
+        (_, StatementLocation::ForeignKey(fk)) => {
+            rename(&fk.source_schema, &mut fk.source_table);
+            rename(&fk.target_schema, &mut fk.target_table);
+        }
+        _ => {}
+    });
+}
+
+/// Schema and name of the table that owns a sequence, according to the statements so far
+fn sequence_owner(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+) -> Option<(String, String)> {
+    nodes
+        .iter()
+        .find_map(|n| match n {
+            StatementLocation::Sequence(s) if s.schema == schema && s.name == name => s
+                .owner()
+                .map(|(table_schema, table)| (table_schema.to_string(), table.to_string())),
+            _ => None,
+        })
+        .or_else(|| identity_owner(nodes, schema, name))
+}
+
+/// Schema and name of the table whose identity column created a sequence. The sequence has no
+/// statement of its own, it is created by the column definition, e.g. by pg_dump's
+/// `ALTER TABLE ... ADD GENERATED BY DEFAULT AS IDENTITY (SEQUENCE NAME ...)`.
+fn identity_owner(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+) -> Option<(String, String)> {
+    nodes.iter().find_map(|n| match n {
+        StatementLocation::Table(t)
+            if t.identity_sequences
+                .iter()
+                .any(|(s, n)| s == schema && n == name) =>
+        {
+            Some((t.schema.clone(), t.name.clone()))
+        }
+        _ => None,
+    })
+}
+
+/// Schema and name of the sequences created by identity constraints, given with the column they
+/// are defined on
+fn identity_sequences<'a>(
+    schema: &str,
+    table: &str,
+    constraints: impl Iterator<Item = (&'a str, &'a Node)>,
+) -> Vec<(String, String)> {
+    constraints
+        .filter_map(|(column, constraint)| {
+            let Some(NodeEnum::Constraint(c)) = &constraint.node else {
+                return None;
+            };
+            if c.contype() != pg_query::protobuf::ConstrType::ConstrIdentity {
+                return None;
+            }
+
+            // Without `SEQUENCE NAME`, Postgres names the sequence after the table and column
+            let sequence = c.options.iter().find_map(|o| match &o.node {
+                Some(NodeEnum::DefElem(d)) if d.defname == "sequence_name" => {
+                    match d.arg.as_ref()?.node.as_ref()? {
+                        NodeEnum::List(l) => Some(extract_names(&l.items, "identity sequence")),
+                        _ => None,
+                    }
                 }
-            } else {
-                panic!("Expected List for schemas in AlterDefaultPrivilegesStmt");
+                _ => None,
+            });
+            Some(match sequence.as_deref() {
+                Some([sequence]) => (schema.to_string(), sequence.clone()),
+                Some([sequence_schema, sequence]) => (sequence_schema.clone(), sequence.clone()),
+                _ => (schema.to_string(), format!("{}_{}_seq", table, column)),
+            })
+        })
+        .collect()
+}
+
+/// Schema and name of every function called in the expressions, without duplicates.
+/// Unqualified names are assumed to be in `public`. The node iterator of pg_query only covers
+/// queries, so the expressions are wrapped into a `SELECT`.
+fn called_functions(expressions: &[&Node]) -> Vec<(String, String)> {
+    let select = NodeEnum::SelectStmt(Box::new(SelectStmt {
+        target_list: expressions
+            .iter()
+            .map(|e| Node {
+                node: Some(NodeEnum::ResTarget(Box::new(ResTarget {
+                    val: Some(Box::new((*e).clone())),
+                    ..Default::default()
+                }))),
+            })
+            .collect(),
+        ..Default::default()
+    }));
+
+    let mut functions: Vec<(String, String)> = Vec::new();
+    select.nodes().into_iter().for_each(|(node, ..)| {
+        if let NodeRef::FuncCall(f) = node {
+            let function = match extract_names(&f.funcname, "function call").as_slice() {
+                [name] => ("public".to_string(), name.clone()),
+                [schema, name] => (schema.clone(), name.clone()),
+                _ => return,
             };
+            if !functions.contains(&function) {
+                functions.push(function);
+            }
+        }
+    });
 
-            nodes.push(StatementLocation::Schema(Schema {
-                name: schema_name,
-                sql: sql.to_string(),
-            }));
+    functions
+}
+
+/// The table without the foreign keys of its columns and constraints, and the foreign keys with
+/// their columns and the name Postgres would choose for them. `None` if there are none.
+fn extract_inline_foreign_keys(stmt: &CreateStmt) -> Option<(CreateStmt, Vec<Constraint>)> {
+    let is_foreign_key = |n: &Node| matches!(&n.node, Some(NodeEnum::Constraint(c)) if c.contype() == ConstrType::ConstrForeign);
+
+    let mut table = stmt.clone();
+    let mut foreign_keys = Vec::new();
+    table
+        .table_elts
+        .iter_mut()
+        .for_each(|element| match &mut element.node {
+            Some(NodeEnum::ColumnDef(column)) => {
+                // `DEFERRABLE` and the like are nodes of their own after the constraint they belong to
+                let mut foreign_key: Option<Box<Constraint>> = None;
+                let mut constraints = Vec::new();
+                column.constraints.drain(..).for_each(|n| match n.node {
+                    Some(NodeEnum::Constraint(mut c))
+                        if c.contype() == ConstrType::ConstrForeign =>
+                    {
+                        // A column constraint references the column it is defined on
+                        c.fk_attrs = vec![Node {
+                            node: Some(NodeEnum::String(PgString {
+                                sval: column.colname.clone(),
+                            })),
+                        }];
+                        foreign_keys.extend(foreign_key.replace(c).map(|c| *c));
+                    }
+                    Some(NodeEnum::Constraint(ref attribute)) if foreign_key.is_some() => {
+                        let c = foreign_key.as_mut().expect("Missing foreign key");
+                        match attribute.contype() {
+                            ConstrType::ConstrAttrDeferrable => c.deferrable = true,
+                            ConstrType::ConstrAttrNotDeferrable => c.deferrable = false,
+                            ConstrType::ConstrAttrDeferred => {
+                                c.deferrable = true;
+                                c.initdeferred = true;
+                            }
+                            ConstrType::ConstrAttrImmediate => c.initdeferred = false,
+                            _ => {
+                                foreign_keys.extend(foreign_key.take().map(|c| *c));
+                                constraints.push(n);
+                            }
+                        }
+                    }
+                    _ => constraints.push(n),
+                });
+                foreign_keys.extend(foreign_key.map(|c| *c));
+                column.constraints = constraints;
+            }
+            Some(NodeEnum::Constraint(c)) if c.contype() == ConstrType::ConstrForeign => {
+                foreign_keys.push((**c).clone())
+            }
+            _ => {}
+        });
+    table.table_elts.retain(|n| !is_foreign_key(n));
+
+    if foreign_keys.is_empty() {
+        return None;
+    }
+
+    let table_name = &stmt
+        .relation
+        .as_ref()
+        .expect("Missing relation in CreateStmt")
+        .relname;
+    foreign_keys
+        .iter_mut()
+        .filter(|c| c.conname.is_empty())
+        .for_each(|c| {
+            let columns = c
+                .fk_attrs
+                .iter()
+                .map(|n| get_sval(&n.node))
+                .collect::<Vec<_>>();
+            c.conname = constraint_name(table_name, &columns.join("_"), "fkey");
+        });
+
+    Some((table, foreign_keys))
+}
+
+/// The name Postgres chooses for an unnamed constraint, e.g. `orders_user_id_fkey`. The table and
+/// column parts are shortened alike to fit the 63 bytes of an identifier.
+fn constraint_name(table: &str, columns: &str, label: &str) -> String {
+    let available = 63 - label.len() - 2;
+    let mut table = table.to_string();
+    let mut columns = columns.to_string();
+    while table.len() + columns.len() > available {
+        if table.len() > columns.len() {
+            table.pop();
+        } else {
+            columns.pop();
+        }
+    }
+
+    format!("{}_{}_{}", table, columns, label)
+}
+
+/// Rebuild a single-command ALTER TABLE statement for every command and deparse it
+fn split_alter_table(stmt: &AlterTableStmt) -> Vec<String> {
+    stmt.cmds
+        .iter()
+        .map(|cmd| {
+            NodeEnum::AlterTableStmt(AlterTableStmt {
+                cmds: vec![cmd.clone()],
+                ..stmt.clone()
+            })
+            .deparse()
+            .expect("Failed to deparse ALTER TABLE command")
+        })
+        .collect()
+}
+
+/// The location of a statement on an index, which is written to the file of the index, or of its
+/// table if the index belongs to a constraint
+fn index_location(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+    sql: &str,
+) -> Option<StatementLocation> {
+    if let Some(table) = find_index_table(nodes, schema, name) {
+        return Some(StatementLocation::Index(Index {
+            schema: schema.to_string(),
+            name: name.to_string(),
+            table,
+            concurrent: false,
+            sql: sql.to_string(),
+        }));
+    }
+
+    find_constraint_table(nodes, schema, name).map(|table| {
+        StatementLocation::Table(Table {
+            schema: schema.to_string(),
+            name: table,
+            sql: sql.to_string(),
+            inherits: Vec::new(),
+            identity_sequences: Vec::new(),
+            constraints: Vec::new(),
+        })
+    })
+}
+
+/// Table of an index created with `CREATE INDEX`
+fn find_index_table(nodes: &[StatementLocation], schema: &str, name: &str) -> Option<String> {
+    nodes.iter().find_map(|node| match node {
+        StatementLocation::Index(i) if i.schema == schema && i.name == name => {
+            Some(i.table.clone())
         }
-        _ => panic!("Unsupported node:\n{:?} '{}'", node, sql),
+        _ => None,
+    })
+}
+
+/// Table of a constraint added with `ALTER TABLE ... ADD CONSTRAINT`, e.g. a primary key
+fn find_constraint_table(nodes: &[StatementLocation], schema: &str, name: &str) -> Option<String> {
+    nodes.iter().find_map(|node| match node {
+        StatementLocation::Table(t)
+            if t.schema == schema && t.constraints.iter().any(|c| c == name) =>
+        {
+            Some(t.name.clone())
+        }
+        _ => None,
+    })
+}
+
+/// Name of the variant of a node, e.g. `CreateStmt`
+pub(crate) fn node_kind(node: &NodeEnum) -> String {
+    // The debug output starts with the variant name, e.g. `CreateStmt(CreateStmt { .. })`
+    let debug = format!("{:?}", node);
+    debug.split('(').next().unwrap_or(&debug).to_string()
+}
+
+/// Location of a text search configuration, dictionary, template or parser
+fn text_search(names: &[String], kind: ObjectType, sql: &str) -> StatementLocation {
+    let kind = match kind {
+        ObjectType::ObjectTsconfiguration => "configuration",
+        ObjectType::ObjectTsdictionary => "dictionary",
+        ObjectType::ObjectTstemplate => "template",
+        ObjectType::ObjectTsparser => "parser",
+        _ => panic!("Unsupported text search object type: {:?}", kind),
     };
+
+    StatementLocation::TextSearch(TextSearch {
+        schema: get_schema_or_default(names).to_string(),
+        name: names
+            .last()
+            .expect("Missing text search object name")
+            .to_string(),
+        kind: kind.to_string(),
+        sql: sql.to_string(),
+    })
+}
+
+/// The statements that routing splits a statement into, e.g. one `GRANT` per object. Other
+/// statements are returned as they are.
+pub(crate) fn split_parts(sql: &str) -> Vec<String> {
+    match parse_sql(sql) {
+        NodeEnum::AlterTableStmt(n) if n.cmds.len() > 1 => split_alter_table(&n),
+        NodeEnum::GrantStmt(n) if n.objects.len() > 1 => split_grant(&n),
+        NodeEnum::DropStmt(n) if n.objects.len() > 1 => split_drop(&n),
+        NodeEnum::AlterDefaultPrivilegesStmt(n) if default_privileges_schemas(&n).len() > 1 => {
+            split_default_privileges(&n)
+        }
+        _ => vec![sql.to_string()],
+    }
+}
+
+/// Split a grant on multiple objects into one grant per object
+fn split_grant(stmt: &GrantStmt) -> Vec<String> {
+    stmt.objects
+        .iter()
+        .map(|object| {
+            NodeEnum::GrantStmt(GrantStmt {
+                objects: vec![object.clone()],
+                ..stmt.clone()
+            })
+            .deparse()
+            .expect("Failed to deparse GRANT statement")
+        })
+        .collect()
+}
+
+/// Move the drop of an object that is only created later in the dump, like a `DROP ... IF EXISTS`
+/// before its `CREATE`, from the index of the drops to the file of the object
+fn resolve_drops(nodes: &mut [StatementLocation]) {
+    let resolved = nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, n)| {
+            let StatementLocation::Drop(drop) = n else {
+                return None;
+            };
+            let NodeEnum::DropStmt(stmt) = parse_sql(&drop.sql) else {
+                return None;
+            };
+            let names = object_names(stmt.objects.first()?);
+            let object = find_object(
+                &nodes[index + 1..],
+                stmt.remove_type(),
+                &names,
+                &mut Tracer::default(),
+            )?;
+            Some((index, object.in_dump_order(&drop.sql)))
+        })
+        .collect::<Vec<_>>();
+
+    resolved
+        .into_iter()
+        .for_each(|(index, node)| nodes[index] = node);
+}
+
+/// Split a drop of multiple objects into one drop per object
+fn split_drop(stmt: &DropStmt) -> Vec<String> {
+    stmt.objects
+        .iter()
+        .map(|object| {
+            NodeEnum::DropStmt(DropStmt {
+                objects: vec![object.clone()],
+                ..stmt.clone()
+            })
+            .deparse()
+            .expect("Failed to deparse DROP statement")
+        })
+        .collect()
+}
+
+/// The schemas of the `IN SCHEMA` clause of an `ALTER DEFAULT PRIVILEGES` statement
+fn default_privileges_schemas(stmt: &AlterDefaultPrivilegesStmt) -> Vec<String> {
+    stmt.options
+        .iter()
+        .find_map(|o| match &o.node {
+            Some(NodeEnum::DefElem(d)) if d.defname == "schemas" => {
+                match d.arg.as_ref().and_then(|a| a.node.as_ref()) {
+                    Some(NodeEnum::List(l)) => {
+                        Some(extract_names(&l.items, "default privileges schemas"))
+                    }
+                    other => panic!(
+                        "Expected List for schemas in AlterDefaultPrivilegesStmt, found {:?}",
+                        other
+                    ),
+                }
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Split an `ALTER DEFAULT PRIVILEGES` statement for multiple schemas into one statement per
+/// schema. Other options like `FOR ROLE` are kept.
+fn split_default_privileges(stmt: &AlterDefaultPrivilegesStmt) -> Vec<String> {
+    default_privileges_schemas(stmt)
+        .iter()
+        .map(|schema| {
+            let options = stmt
+                .options
+                .iter()
+                .map(|o| match &o.node {
+                    Some(NodeEnum::DefElem(d)) if d.defname == "schemas" => Node {
+                        node: Some(NodeEnum::DefElem(Box::new(DefElem {
+                            arg: Some(Box::new(Node {
+                                node: Some(NodeEnum::List(List {
+                                    items: vec![Node {
+                                        node: Some(NodeEnum::String(PgString {
+                                            sval: schema.clone(),
+                                        })),
+                                    }],
+                                })),
+                            })),
+                            ..(**d).clone()
+                        }))),
+                    },
+                    _ => o.clone(),
+                })
+                .collect();
+
+            NodeEnum::AlterDefaultPrivilegesStmt(AlterDefaultPrivilegesStmt {
+                options,
+                ..stmt.clone()
+            })
+            .deparse()
+            .expect("Failed to deparse ALTER DEFAULT PRIVILEGES statement")
+        })
+        .collect()
+}
+
+/// Qualified name of a dropped or altered object, e.g. `[schema, table, trigger]` for a trigger
+fn object_names(object: &Node) -> Vec<String> {
+    match &object.node {
+        // The source and target type of a cast
+        Some(NodeEnum::List(l))
+            if l.items
+                .iter()
+                .any(|i| matches!(i.node, Some(NodeEnum::TypeName(_)))) =>
+        {
+            l.items.iter().map(|i| object_names(i).join(".")).collect()
+        }
+        Some(NodeEnum::List(l)) => extract_names(&l.items, "object"),
+        Some(NodeEnum::ObjectWithArgs(o)) => extract_names(&o.objname, "function"),
+        Some(NodeEnum::TypeName(t)) => extract_names(&t.names, "type"),
+        Some(NodeEnum::String(s)) => vec![s.sval.clone()],
+        other => panic!("Unexpected object: {:?}", other),
+    }
+}
+
+/// Find the node of an object that is dropped or altered. Triggers and policies are identified by
+/// their table as well as their name.
+fn find_object<'a>(
+    nodes: &'a [StatementLocation],
+    remove_type: ObjectType,
+    names: &[String],
+    tracer: &mut Tracer,
+) -> Option<&'a StatementLocation> {
+    let categories: &[&str] = match remove_type {
+        ObjectType::ObjectTable => &["Table", "Partition"],
+        ObjectType::ObjectForeignTable => &["ForeignTable"],
+        ObjectType::ObjectView | ObjectType::ObjectMatview => &["View"],
+        ObjectType::ObjectIndex => &["Index"],
+        ObjectType::ObjectSequence => &["Sequence"],
+        ObjectType::ObjectFunction | ObjectType::ObjectProcedure => {
+            &["TriggerFunction", "Function"]
+        }
+        ObjectType::ObjectAggregate => &["Aggregate"],
+        ObjectType::ObjectType => &["EnumNode", "CompositeType"],
+        ObjectType::ObjectCollation => &["Collation"],
+        ObjectType::ObjectSchema => &["Schema"],
+        ObjectType::ObjectTrigger => &["Trigger"],
+        ObjectType::ObjectPolicy => &["Policy"],
+        // Casts are not in a schema and named after their types
+        ObjectType::ObjectCast => {
+            let name = names.join("__");
+            let found = nodes
+                .iter()
+                .find(|node| node.category() == "Cast" && node.name() == name);
+            tracer.lookup("cast", "", &name, found.is_some());
+            return found;
+        }
+        _ => &[],
+    };
+
+    let (schema, table, name) = match (remove_type, names) {
+        (ObjectType::ObjectSchema, [name]) => (name.as_str(), None, name.as_str()),
+        (ObjectType::ObjectTrigger | ObjectType::ObjectPolicy, [table, name]) => {
+            ("public", Some(table.as_str()), name.as_str())
+        }
+        (ObjectType::ObjectTrigger | ObjectType::ObjectPolicy, [schema, table, name]) => {
+            (schema.as_str(), Some(table.as_str()), name.as_str())
+        }
+        (ObjectType::ObjectTrigger | ObjectType::ObjectPolicy, _) => return None,
+        (_, [name]) => ("public", None, name.as_str()),
+        (_, [schema, name]) => (schema.as_str(), None, name.as_str()),
+        _ => return None,
+    };
+
+    let found = nodes.iter().find(|node| {
+        let same_table = match node.object() {
+            StatementLocation::Trigger(t) => Some(t.table.as_str()) == table,
+            StatementLocation::Policy(p) => Some(p.table.as_str()) == table,
+            _ => true,
+        };
+
+        categories.contains(&node.category())
+            && node.schema() == Some(schema)
+            && node.name() == name
+            && same_table
+    });
+    tracer.lookup("object", schema, name, found.is_some());
+    found
+}
+
+/// Name of a role as written in SQL, e.g. `postgres` or `current_user`
+fn role_name(role: &RoleSpec) -> String {
+    match role.roletype() {
+        RoleSpecType::RolespecPublic => "public".to_string(),
+        RoleSpecType::RolespecCurrentUser => "current_user".to_string(),
+        RoleSpecType::RolespecCurrentRole => "current_role".to_string(),
+        RoleSpecType::RolespecSessionUser => "session_user".to_string(),
+        _ => role.rolename.clone(),
+    }
+}
+
+/// Check whether a statement contains the `PROCEDURE` keyword
+fn uses_execute_procedure(sql: &str) -> bool {
+    pg_query::scan(sql)
+        .expect("Failed to scan SQL")
+        .tokens
+        .iter()
+        .any(|t| t.token() == Token::Procedure)
 }
 
 pub fn get_sval(n: &Option<pg_query::protobuf::node::Node>) -> String {
@@ -877,7 +3180,87 @@ pub fn get_sval(n: &Option<pg_query::protobuf::node::Node>) -> String {
     }
 }
 
-fn parse_sql(sql: &str) -> pg_query::NodeEnum {
+/// The line comments right before a statement, e.g. `-- Users of the app`. The deparser drops
+/// them, but they often document the object.
+pub(crate) fn leading_comments(sql: &str) -> String {
+    sql.lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with("--"))
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// Format a statement consistently by running it through the deparser. The deparser does not
+/// cover every construct faithfully, so if the result does not parse to the same statement, the
+/// statement is kept as written.
+pub fn deparse_sql(sql: &str) -> String {
+    let deparsed = pg_query::parse(sql)
+        .expect("Failed to parse SQL")
+        .deparse()
+        .expect("Failed to deparse SQL");
+
+    if syntax_tree(&deparsed) != syntax_tree(sql) {
+        log::warn!(
+            "Keeping a statement as written, as the deparser changes its meaning: '{}'",
+            excerpt(sql)
+        );
+        return sql.trim().to_string();
+    }
+
+    format!("{};", deparsed)
+}
+
+/// The syntax tree of a statement without the positions of its nodes, which differ between
+/// formattings
+fn syntax_tree(sql: &str) -> Option<String> {
+    let stmts = pg_query::parse(sql).ok()?.protobuf.stmts;
+    let tree = format!("{:?}", stmts.iter().map(|s| &s.stmt).collect::<Vec<_>>());
+
+    let mut stripped = String::with_capacity(tree.len());
+    let mut rest = tree.as_str();
+    while let Some(start) = rest.find("location: ") {
+        let (before, after) = rest.split_at(start + "location: ".len());
+        stripped.push_str(before);
+        rest = after.trim_start_matches(|c: char| c.is_ascii_digit() || c == '-');
+    }
+    stripped.push_str(rest);
+    Some(stripped)
+}
+
+/// Parse every top-level statement of a chunk together with its own SQL. The split should yield
+/// a single statement per chunk, but if it does not, no statement must get lost.
+fn parse_statements(sql: &str) -> Vec<(String, NodeEnum)> {
+    let stmts = pg_query::parse(sql)
+        .expect("Failed to parse SQL")
+        .protobuf
+        .stmts;
+
+    if stmts.len() == 1 {
+        return vec![(sql.to_string(), parse_sql(sql))];
+    }
+
+    stmts
+        .iter()
+        .map(|raw| {
+            let start = raw.stmt_location as usize;
+            // A length of 0 means that the statement extends to the end of the string
+            let end = if raw.stmt_len == 0 {
+                sql.len()
+            } else {
+                start + raw.stmt_len as usize
+            };
+            let node = raw
+                .stmt
+                .as_ref()
+                .and_then(|n| n.node.clone())
+                .expect("Missing statement in parsed SQL");
+            (sql[start..end].trim().to_string(), node)
+        })
+        .collect()
+}
+
+pub fn parse_sql(sql: &str) -> pg_query::NodeEnum {
     pg_query::parse(sql)
         .expect("Failed to parse SQL")
         .protobuf
@@ -896,32 +3279,32 @@ fn extract_names(items: &[Node], _context: &str) -> Vec<String> {
         .collect::<Vec<_>>()
 }
 
-/// Helper to check if a name exists in nodes of a specific type
-fn find_node_by_name<F>(nodes: &[StatementLocation], schema: &str, name: &str, matcher: F) -> bool
-where
-    F: Fn(&StatementLocation) -> Option<(&String, &String)>,
-{
-    nodes.iter().any(|node| {
-        if let Some((node_schema, node_name)) = matcher(node) {
-            node_name == name && node_schema == schema
-        } else {
-            false
-        }
-    })
+/// Qualified name of a type, e.g. `api.my_type`
+fn type_name(t: &pg_query::protobuf::TypeName) -> String {
+    extract_names(&t.names, "type name").join(".")
 }
 
 /// Helper to check if a name exists in nodes of a specific type
-fn nodes_by_name<'a, F>(nodes: &'a[StatementLocation], schema: &'a str, name: &'a str, matcher: F) -> Vec<&'a StatementLocation>
+fn find_node_by_name<F>(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+    kind: &'static str,
+    tracer: &mut Tracer,
+    matcher: F,
+) -> bool
 where
     F: Fn(&StatementLocation) -> Option<(&String, &String)>,
 {
-    nodes.iter().filter(|node| {
+    let found = nodes.iter().any(|node| {
         if let Some((node_schema, node_name)) = matcher(node) {
             node_name == name && node_schema == schema
         } else {
             false
         }
-    }).collect()
+    });
+    tracer.lookup(kind, schema, name, found);
+    found
 }
 
 /// Validate that a list of items has exactly the expected count
@@ -931,12 +3314,17 @@ fn validate_item_count(items: &[String], expected: usize, context: &str) {
     }
 }
 
-/// Extract schema and name from a qualified name list
+/// Extract schema and name from a qualified name list. Unqualified names are in `public`.
 fn extract_schema_and_name<'a>(items: &'a [String], context: &str) -> (&'a str, &'a str) {
-    validate_item_count(items, 2, context);
-    let schema = &items[0];
-    let name = &items[1];
-    (schema, name)
+    match items {
+        [name] => ("public", name),
+        [schema, name] => (schema, name),
+        _ => panic!(
+            "Expected 1 or 2 items in {}, found {}",
+            context,
+            items.len()
+        ),
+    }
 }
 
 /// Helper to get schema from a name list, defaults to "public" if only one item
@@ -948,9 +3336,18 @@ fn get_schema_or_default(names: &[String]) -> &str {
     }
 }
 
+/// The schema of a relation, which is empty if the dump relies on the search path
+pub(crate) fn schema_or_public(schema: &str) -> String {
+    if schema.is_empty() {
+        "public".to_string()
+    } else {
+        schema.to_string()
+    }
+}
+
 /// Check if a table with given schema and name exists
-fn find_table(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
-    find_node_by_name(nodes, schema, name, |node| {
+fn find_table(nodes: &[StatementLocation], schema: &str, name: &str, tracer: &mut Tracer) -> bool {
+    find_node_by_name(nodes, schema, name, "table", tracer, |node| {
         if let StatementLocation::Table(t) = node {
             Some((&t.schema, &t.name))
         } else {
@@ -959,9 +3356,25 @@ fn find_table(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
     })
 }
 
+/// Check if a foreign table with given schema and name exists
+fn find_foreign_table(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+    tracer: &mut Tracer,
+) -> bool {
+    find_node_by_name(nodes, schema, name, "foreign_table", tracer, |node| {
+        if let StatementLocation::ForeignTable(t) = node {
+            Some((&t.schema, &t.name))
+        } else {
+            None
+        }
+    })
+}
+
 /// Check if a view with given schema and name exists
-fn find_view(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
-    find_node_by_name(nodes, schema, name, |node| {
+fn find_view(nodes: &[StatementLocation], schema: &str, name: &str, tracer: &mut Tracer) -> bool {
+    find_node_by_name(nodes, schema, name, "view", tracer, |node| {
         if let StatementLocation::View(v) = node {
             Some((&v.schema, &v.name))
         } else {
@@ -970,9 +3383,25 @@ fn find_view(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
     })
 }
 
+/// Check if a sequence with given schema and name exists
+fn find_sequence(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+    tracer: &mut Tracer,
+) -> bool {
+    find_node_by_name(nodes, schema, name, "sequence", tracer, |node| {
+        if let StatementLocation::Sequence(s) = node {
+            Some((&s.schema, &s.name))
+        } else {
+            None
+        }
+    })
+}
+
 /// Check if an enum type with given schema and name exists
-fn find_enum(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
-    find_node_by_name(nodes, schema, name, |node| {
+fn find_enum(nodes: &[StatementLocation], schema: &str, name: &str, tracer: &mut Tracer) -> bool {
+    find_node_by_name(nodes, schema, name, "enum", tracer, |node| {
         if let StatementLocation::EnumNode(e) = node {
             Some((&e.schema, &e.name))
         } else {
@@ -982,8 +3411,13 @@ fn find_enum(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
 }
 
 /// Check if a composite type with given schema and name exists
-fn find_composite_type(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
-    find_node_by_name(nodes, schema, name, |node| {
+fn find_composite_type(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+    tracer: &mut Tracer,
+) -> bool {
+    find_node_by_name(nodes, schema, name, "composite_type", tracer, |node| {
         if let StatementLocation::CompositeType(t) = node {
             Some((&t.schema, &t.name))
         } else {
@@ -993,8 +3427,13 @@ fn find_composite_type(nodes: &[StatementLocation], schema: &str, name: &str) ->
 }
 
 /// Check if a trigger function with given schema and name exists
-fn find_trigger_function(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
-    find_node_by_name(nodes, schema, name, |node| {
+fn find_trigger_function(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+    tracer: &mut Tracer,
+) -> bool {
+    find_node_by_name(nodes, schema, name, "trigger_function", tracer, |node| {
         if let StatementLocation::TriggerFunction(t) = node {
             Some((&t.schema, &t.name))
         } else {
@@ -1004,8 +3443,13 @@ fn find_trigger_function(nodes: &[StatementLocation], schema: &str, name: &str)
 }
 
 /// Check if a function with given schema and name exists
-fn find_function(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
-    find_node_by_name(nodes, schema, name, |node| {
+fn find_function(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+    tracer: &mut Tracer,
+) -> bool {
+    find_node_by_name(nodes, schema, name, "function", tracer, |node| {
         if let StatementLocation::Function(t) = node {
             Some((&t.schema, &t.name))
         } else {
@@ -1014,9 +3458,29 @@ fn find_function(nodes: &[StatementLocation], schema: &str, name: &str) -> bool
     })
 }
 
+/// The schema of the extension with the given name, if it exists
+fn find_extension(nodes: &[StatementLocation], name: &str, tracer: &mut Tracer) -> Option<String> {
+    let schema = nodes.iter().find_map(|node| match node {
+        StatementLocation::Extension(e) if e.name == name => Some(e.schema.clone()),
+        _ => None,
+    });
+    tracer.lookup(
+        "extension",
+        schema.as_deref().unwrap_or_default(),
+        name,
+        schema.is_some(),
+    );
+    schema
+}
+
 /// Check if a function with given schema and name exists
-fn find_aggregate(nodes: &[StatementLocation], schema: &str, name: &str) -> bool {
-    find_node_by_name(nodes, schema, name, |node| {
+fn find_aggregate(
+    nodes: &[StatementLocation],
+    schema: &str,
+    name: &str,
+    tracer: &mut Tracer,
+) -> bool {
+    find_node_by_name(nodes, schema, name, "aggregate", tracer, |node| {
         if let StatementLocation::Aggregate(t) = node {
             Some((&t.schema, &t.name))
         } else {
@@ -1024,3 +3488,607 @@ fn find_aggregate(nodes: &[StatementLocation], schema: &str, name: &str) -> bool
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn syntax_tree_ignores_locations() {
+        let tree = syntax_tree("SELECT a FROM public.t WHERE id = 1").expect("Failed to parse");
+        assert!(tree.contains("location: ,"));
+        assert_eq!(
+            Some(tree),
+            syntax_tree("select a\n  from   public.t\n where id=1;")
+        );
+    }
+
+    #[test]
+    fn syntax_tree_differs_between_statements() {
+        assert_ne!(syntax_tree("SELECT 1"), syntax_tree("SELECT 2"));
+        assert_ne!(
+            syntax_tree("SELECT a FROM t"),
+            syntax_tree("SELECT \"A\" FROM t")
+        );
+        assert_eq!(syntax_tree("SELECT ("), None);
+    }
+
+    #[test]
+    fn deparsed_statements_parse_to_the_same_tree() {
+        [
+            "CREATE TABLE public.t (id bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY, name text NOT NULL DEFAULT 'x')",
+            "CREATE FUNCTION public.f(a int) RETURNS int LANGUAGE sql STABLE AS $$ SELECT a + 1 $$",
+            "CREATE POLICY p ON public.t FOR SELECT TO authenticated USING (auth.uid() = owner_id)",
+            "ALTER TABLE ONLY public.t ADD CONSTRAINT t_fkey FOREIGN KEY (o) REFERENCES public.o (id) ON DELETE CASCADE",
+            "GRANT SELECT, INSERT ON TABLE public.t TO anon",
+            "COMMENT ON COLUMN public.t.name IS 'The name'",
+        ]
+        .into_iter()
+        .for_each(|sql| {
+            let deparsed = deparse_sql(sql);
+            assert_ne!(deparsed, sql);
+            assert_eq!(syntax_tree(&deparsed), syntax_tree(sql), "{}", deparsed);
+        });
+    }
+
+    #[test]
+    fn grant_on_several_tables() {
+        let nodes = get_nodes(
+            "CREATE TABLE api.t1 (id int);
+            CREATE TABLE api.t2 (id int);
+            CREATE TABLE api.t3 (id int);
+            GRANT SELECT ON api.t1, api.t2, api.t3 TO anon;",
+            &Options::default(),
+        );
+        let grants = nodes[3..]
+            .iter()
+            .map(|n| (n.path(Path::new(""), &nodes), n.sql()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            grants,
+            (1..=3)
+                .map(|i| (
+                    PathBuf::from(format!("api/tables/t{}.sql", i)),
+                    format!("GRANT select ON api.t{} TO anon;", i)
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn grant_on_several_functions() {
+        let nodes = get_nodes(
+            "CREATE FUNCTION api.a() RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;
+            CREATE FUNCTION api.b(x int) RETURNS int LANGUAGE sql AS $$ SELECT x $$;
+            GRANT EXECUTE ON FUNCTION api.a(), api.b(int) TO authenticated;",
+            &Options::default(),
+        );
+        let grants = nodes[2..]
+            .iter()
+            .map(|n| n.path(Path::new(""), &nodes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            grants,
+            vec![
+                PathBuf::from("api/functions/a.sql"),
+                PathBuf::from("api/functions/b.sql")
+            ]
+        );
+        assert!(
+            nodes[2].sql().contains("api.a()") && !nodes[2].sql().contains("api.b"),
+            "{}",
+            nodes[2].sql()
+        );
+        assert!(
+            nodes[3].sql().contains("api.b(int)") && !nodes[3].sql().contains("api.a"),
+            "{}",
+            nodes[3].sql()
+        );
+    }
+
+    #[test]
+    fn quoted_mixed_case_table() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.\"MyTable\" (\"Id\" int);
+            COMMENT ON TABLE public.\"MyTable\" IS 'Mixed case';
+            COMMENT ON COLUMN public.\"MyTable\".\"Id\" IS 'Mixed case column';
+            GRANT SELECT ON TABLE public.\"MyTable\" TO anon;",
+            &Options::default(),
+        );
+        let path = Path::new("public/tables/MyTable.sql");
+        nodes
+            .iter()
+            .for_each(|n| assert_eq!(n.path(Path::new(""), &nodes), path, "{}", n.sql()));
+        assert_eq!(
+            nodes[1].sql(),
+            "COMMENT ON TABLE \"public\".\"MyTable\" IS 'Mixed case';"
+        );
+        assert_eq!(
+            nodes[2].sql(),
+            "COMMENT ON COLUMN \"public\".\"MyTable\".\"Id\" IS 'Mixed case column';"
+        );
+
+        // An unquoted name is folded to lower case, so it is another table
+        let (_, issues) = get_nodes_with_issues(
+            "CREATE TABLE public.\"MyTable\" (id int);
+            COMMENT ON TABLE public.MyTable IS 'Folded';",
+            &Options::default(),
+        );
+        assert!(matches!(
+            issues.as_slice(),
+            [(2, _, ParseIssue::Unsupported(_))]
+        ));
+    }
+
+    #[test]
+    fn function_attributes_survive() {
+        [
+            (
+                "CREATE FUNCTION public.running_sum(int) RETURNS bigint LANGUAGE c WINDOW IMMUTABLE AS 'MODULE_PATHNAME', 'running_sum'",
+                &["WINDOW", "IMMUTABLE"][..],
+            ),
+            (
+                "CREATE FUNCTION public.is_positive(n int) RETURNS boolean LANGUAGE sql LEAKPROOF STRICT SUPPORT public.is_positive_support AS $$ SELECT n > 0 $$",
+                // The deparser spells `STRICT` out
+                &["LEAKPROOF", "RETURNS NULL ON NULL INPUT", "SUPPORT public.is_positive_support"][..],
+            ),
+        ]
+        .into_iter()
+        .for_each(|(sql, attributes)| {
+            let nodes = get_nodes(sql, &Options::default());
+            assert!(matches!(nodes.as_slice(), [StatementLocation::Function(_)]));
+            assert_eq!(nodes[0].sql(), format!("{};", sql));
+
+            let deparsed = deparse_sql(sql);
+            assert_eq!(syntax_tree(&deparsed), syntax_tree(sql), "{}", deparsed);
+            attributes
+                .iter()
+                .for_each(|attribute| assert!(deparsed.contains(attribute), "{}", deparsed));
+        });
+    }
+
+    #[test]
+    fn comment_on_extension_in_the_file_of_the_extension() {
+        let nodes = get_nodes(
+            "CREATE EXTENSION IF NOT EXISTS pgcrypto WITH SCHEMA extensions;
+            COMMENT ON EXTENSION pgcrypto IS 'Cryptographic functions';",
+            &Options::default(),
+        );
+        let path = Path::new("extensions/extensions/pgcrypto.sql");
+        assert_eq!(nodes[0].path(Path::new(""), &nodes), path);
+        assert_eq!(nodes[1].path(Path::new(""), &nodes), path);
+        assert_eq!(
+            nodes[1].sql(),
+            "COMMENT ON EXTENSION \"pgcrypto\" IS 'Cryptographic functions';"
+        );
+
+        let (_, issues) = get_nodes_with_issues(
+            "COMMENT ON EXTENSION pg_trgm IS 'Trigrams';",
+            &Options::default(),
+        );
+        assert!(matches!(
+            issues.as_slice(),
+            [(1, _, ParseIssue::Unsupported(_))]
+        ));
+    }
+
+    #[test]
+    fn deparse_keeps_statement_whose_meaning_changes() {
+        // The deparser drops the space before `NOWAIT`, which makes it part of the table name
+        let sql = "SELECT a FROM t FOR UPDATE OF t NOWAIT";
+        assert_eq!(deparse_sql(sql), sql);
+    }
+
+    #[test]
+    fn session_settings_by_scope() {
+        let options = Options {
+            skip_settings: vec!["statement_timeout".to_string()],
+            ..Options::default()
+        };
+        let (nodes, issues) = get_nodes_with_issues(
+            "SET statement_timeout = 0;
+            RESET statement_timeout;
+            SET LOCAL lock_timeout = 0;
+            SET TRANSACTION ISOLATION LEVEL SERIALIZABLE;
+            SET SESSION search_path = '';
+            RESET check_function_bodies;
+            RESET ALL;",
+            &options,
+        );
+        let sql = nodes
+            .iter()
+            .map(|n| n.sql().trim().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sql,
+            vec![
+                "SET SESSION search_path = '';",
+                "RESET check_function_bodies;"
+            ]
+        );
+        let skipped = issues
+            .iter()
+            .map(|(index, _, issue)| match issue {
+                ParseIssue::Skipped(reason) => (*index, *reason),
+                ParseIssue::Unsupported(what) => panic!("Unsupported {}", what),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            vec![
+                (1, "session setting"),
+                (2, "session setting"),
+                (3, "setting outside a transaction"),
+                (4, "setting outside a transaction"),
+                (7, "RESET ALL"),
+            ]
+        );
+    }
+
+    #[test]
+    fn identity_sequence_in_the_file_of_its_table() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.users (id bigint NOT NULL, name text);
+            ALTER TABLE public.users ALTER COLUMN id ADD GENERATED ALWAYS AS IDENTITY (
+                SEQUENCE NAME public.users_id_seq START WITH 1 INCREMENT BY 1 NO MINVALUE NO MAXVALUE CACHE 1
+            );
+            GRANT ALL ON SEQUENCE public.users_id_seq TO anon;
+            CREATE TABLE public.posts (id bigint GENERATED BY DEFAULT AS IDENTITY, title text);
+            GRANT ALL ON SEQUENCE public.posts_id_seq TO anon;",
+            &Options::default(),
+        );
+        let paths = nodes
+            .iter()
+            .map(|n| n.path(Path::new(""), &nodes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("public/tables/users.sql"),
+                PathBuf::from("public/tables/users.sql"),
+                PathBuf::from("public/tables/users.sql"),
+                PathBuf::from("public/tables/posts.sql"),
+                PathBuf::from("public/tables/posts.sql"),
+            ]
+        );
+    }
+
+    #[test]
+    fn index_of_a_constraint_in_the_file_of_its_table() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.users (id bigint NOT NULL, email text);
+            CREATE TABLE public.posts (id bigint NOT NULL);
+            ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id), ADD CONSTRAINT users_email_key UNIQUE (email);
+            ALTER TABLE ONLY public.posts ADD CONSTRAINT posts_pkey PRIMARY KEY (id);
+            ALTER INDEX public.users_email_key ALTER COLUMN 1 SET STATISTICS 100;
+            ALTER INDEX public.posts_pkey RENAME TO posts_id_key;",
+            &Options::default(),
+        );
+        let paths = nodes
+            .iter()
+            .map(|n| n.path(Path::new(""), &nodes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths[5..],
+            [
+                PathBuf::from("public/tables/users.sql"),
+                PathBuf::from("public/tables/posts.sql")
+            ]
+        );
+    }
+
+    #[test]
+    fn comment_on_identity_column() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.users (id bigint GENERATED ALWAYS AS IDENTITY, email text GENERATED ALWAYS AS (lower(name)) STORED, name text);
+            COMMENT ON COLUMN public.users.id IS 'Identity';
+            COMMENT ON COLUMN public.users.email IS 'Generated';",
+            &Options::default(),
+        );
+        let path = Path::new("public/tables/users.sql");
+        nodes
+            .iter()
+            .for_each(|n| assert_eq!(n.path(Path::new(""), &nodes), path, "{}", n.sql()));
+        assert_eq!(
+            nodes[1].sql(),
+            "COMMENT ON COLUMN \"public\".\"users\".\"id\" IS 'Identity';"
+        );
+        assert_eq!(
+            nodes[2].sql(),
+            "COMMENT ON COLUMN \"public\".\"users\".\"email\" IS 'Generated';"
+        );
+    }
+
+    #[test]
+    fn schema_grant_without_schema_creation() {
+        let options = Options {
+            skip_schema_create: true,
+            ..Options::default()
+        };
+        let (nodes, issues) = get_nodes_with_issues(
+            "CREATE SCHEMA api;
+            GRANT USAGE ON SCHEMA api TO anon;
+            COMMENT ON SCHEMA api IS 'Public API';",
+            &options,
+        );
+        assert!(matches!(
+            issues.as_slice(),
+            [(1, _, ParseIssue::Skipped("schema creation"))]
+        ));
+        let path = Path::new("api/index.sql");
+        nodes
+            .iter()
+            .for_each(|n| assert_eq!(n.path(Path::new(""), &nodes), path, "{}", n.sql()));
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes[0].sql().contains("GRANT USAGE ON SCHEMA api TO anon"));
+    }
+
+    #[test]
+    fn reference_to_a_schema_that_was_not_dumped() {
+        let (nodes, issues) = get_nodes_with_issues(
+            "CREATE TABLE public.users (id int);
+            COMMENT ON TABLE api.foo IS 'Not dumped';
+            GRANT SELECT ON TABLE api.foo TO anon;",
+            &Options::default(),
+        );
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(
+            issues
+                .iter()
+                .map(|(index, _, issue)| (*index, issue.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    2,
+                    ParseIssue::Skipped("reference to a schema that was not dumped")
+                ),
+                (
+                    3,
+                    ParseIssue::Skipped("reference to a schema that was not dumped")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "references api.foo but schema 'api' was not dumped")]
+    fn reference_to_a_schema_that_was_not_dumped_with_strict_schemas() {
+        let options = Options {
+            strict_schemas: true,
+            ..Options::default()
+        };
+        get_nodes_with_issues(
+            "CREATE TABLE public.users (id int);
+            COMMENT ON TABLE api.foo IS 'Not dumped';",
+            &options,
+        );
+    }
+
+    #[test]
+    fn set_schema_in_the_file_of_the_old_schema() {
+        let nodes = get_nodes(
+            "CREATE AGGREGATE public.total(int) (SFUNC = int4pl, STYPE = int);
+            ALTER AGGREGATE public.total(int) SET SCHEMA api;
+            CREATE FUNCTION public.add(a int) RETURNS int LANGUAGE sql AS $$ SELECT a + 1 $$;
+            ALTER FUNCTION public.add(int) SET SCHEMA api;
+            CREATE TYPE public.mood AS ENUM ('happy', 'sad');
+            ALTER TYPE public.mood SET SCHEMA api;",
+            &Options::default(),
+        );
+        let paths = nodes
+            .iter()
+            .map(|n| n.path(Path::new(""), &nodes))
+            .collect::<Vec<_>>();
+        assert_eq!(paths[0], Path::new("public/aggregates/total.sql"));
+        assert_eq!(paths[2], Path::new("public/functions/add.sql"));
+        assert_eq!(paths[4], Path::new("public/enums/mood.sql"));
+        [1, 3, 5]
+            .into_iter()
+            .for_each(|i| assert_eq!(paths[i], paths[i - 1], "{}", nodes[i].sql()));
+    }
+
+    #[test]
+    fn comment_on_attribute_of_a_composite_type() {
+        let nodes = get_nodes(
+            "CREATE TYPE api.address AS (street text, city text);
+            COMMENT ON COLUMN api.address.city IS 'City of the address';",
+            &Options::default(),
+        );
+        let path = Path::new("api/types/address.sql");
+        assert_eq!(nodes[0].path(Path::new(""), &nodes), path);
+        assert_eq!(nodes[1].path(Path::new(""), &nodes), path);
+        assert_eq!(
+            nodes[1].sql(),
+            "COMMENT ON COLUMN \"api\".\"address\".\"city\" IS 'City of the address';"
+        );
+    }
+
+    #[test]
+    fn comments_on_policies_and_constraints() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.teams (id int PRIMARY KEY);
+            CREATE TABLE public.users (id int, team_id int, CONSTRAINT users_id_positive CHECK (id > 0));
+            ALTER TABLE ONLY public.users ADD CONSTRAINT users_team_id_fkey FOREIGN KEY (team_id) REFERENCES public.teams(id);
+            CREATE POLICY read_own ON public.users FOR SELECT USING (true);
+            COMMENT ON POLICY read_own ON public.users IS 'Users read their own rows';
+            COMMENT ON CONSTRAINT users_id_positive ON public.users IS 'Ids are positive';
+            COMMENT ON CONSTRAINT users_team_id_fkey ON public.users IS 'Team of the user';",
+            &Options::default(),
+        );
+        let paths = nodes
+            .iter()
+            .map(|n| n.path(Path::new(""), &nodes))
+            .collect::<Vec<_>>();
+        assert_eq!(paths[4], paths[3]);
+        assert_eq!(paths[4], Path::new("public/policies/users/read_own.sql"));
+        assert_eq!(paths[5], Path::new("public/tables/users.sql"));
+        assert_eq!(paths[6], paths[2]);
+        assert_eq!(
+            paths[6],
+            Path::new("public/fkeys/users/users_team_id_fkey.sql")
+        );
+    }
+
+    #[test]
+    fn normalize_trigger_exec() {
+        let sql = "CREATE FUNCTION public.touch() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+            CREATE TABLE public.t (id int);
+            CREATE TRIGGER touch BEFORE UPDATE ON public.t FOR EACH ROW EXECUTE PROCEDURE public.touch();";
+        let options = Options {
+            normalize_trigger_exec: true,
+            ..Options::default()
+        };
+        let nodes = get_nodes(sql, &options);
+        assert_eq!(
+            nodes[2].sql(),
+            "CREATE TRIGGER touch BEFORE UPDATE ON public.t FOR EACH ROW EXECUTE FUNCTION public.touch();"
+        );
+
+        // Without the option, the trigger is kept as written
+        let nodes = get_nodes(sql, &Options::default());
+        assert!(nodes[2].sql().contains("EXECUTE PROCEDURE public.touch()"));
+    }
+
+    #[test]
+    fn minimal_quote_style() {
+        let options = Options {
+            quote_style: QuoteStyle::Minimal,
+            ..Options::default()
+        };
+        let nodes = get_nodes(
+            "CREATE TABLE public.users (id int);
+            CREATE TABLE public.\"user\" (id int);
+            COMMENT ON TABLE public.users IS 'Users';
+            COMMENT ON TABLE public.\"user\" IS 'Reserved';",
+            &options,
+        );
+        assert_eq!(nodes[2].sql(), "COMMENT ON TABLE public.users IS 'Users';");
+        assert_eq!(
+            nodes[3].sql(),
+            "COMMENT ON TABLE public.\"user\" IS 'Reserved';"
+        );
+    }
+
+    #[test]
+    fn split_constraint_whose_literal_contains_add_constraint() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.teams (id int PRIMARY KEY);
+            CREATE TABLE public.notes (id int, team_id int, body text);
+            ALTER TABLE ONLY public.notes
+                ADD CONSTRAINT notes_body_check CHECK (body <> 'ADD CONSTRAINT x'),
+                ADD CONSTRAINT notes_team_id_fkey FOREIGN KEY (team_id) REFERENCES public.teams(id);",
+            &Options::default(),
+        );
+        let split = nodes[2..]
+            .iter()
+            .map(|n| (n.path(Path::new(""), &nodes), n.sql().to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            split,
+            vec![
+                (
+                    PathBuf::from("public/tables/notes.sql"),
+                    "ALTER TABLE ONLY public.notes ADD CONSTRAINT notes_body_check CHECK (body <> 'ADD CONSTRAINT x');"
+                        .to_string()
+                ),
+                (
+                    PathBuf::from("public/fkeys/notes/notes_team_id_fkey.sql"),
+                    "ALTER TABLE ONLY public.notes ADD CONSTRAINT notes_team_id_fkey FOREIGN KEY (team_id) REFERENCES public.teams (id);"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_logged_and_unlogged_in_the_table_file() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.events (id int);
+            ALTER TABLE public.events SET UNLOGGED;
+            ALTER TABLE public.events SET LOGGED;",
+            &Options::default(),
+        );
+        let path = Path::new("public/tables/events.sql");
+        nodes
+            .iter()
+            .for_each(|n| assert_eq!(n.path(Path::new(""), &nodes), path, "{}", n.sql()));
+        assert_eq!(nodes.len(), 3);
+    }
+
+    #[test]
+    fn comment_with_single_quotes() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.users (name text);
+            COMMENT ON TABLE public.users IS 'O''Brien''s table';
+            COMMENT ON COLUMN public.users.name IS 'O''Brien''s column';",
+            &Options::default(),
+        );
+        assert_eq!(
+            nodes[1].sql(),
+            "COMMENT ON TABLE \"public\".\"users\" IS 'O''Brien''s table';"
+        );
+        assert_eq!(
+            nodes[2].sql(),
+            "COMMENT ON COLUMN \"public\".\"users\".\"name\" IS 'O''Brien''s column';"
+        );
+    }
+
+    #[test]
+    fn comments_round_trip() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.users (name text);
+            COMMENT ON TABLE public.users IS E'Literal \\\\n, not a line break';
+            COMMENT ON COLUMN public.users.name IS 'First line
+second line';",
+            &Options::default(),
+        );
+        let comment = |sql: &str| match parse_sql(sql) {
+            NodeEnum::CommentStmt(c) => c.comment,
+            node => panic!("Expected a comment, found {:?}", node),
+        };
+        assert_eq!(comment(&nodes[1].sql()), "Literal \\n, not a line break");
+        assert_eq!(comment(&nodes[2].sql()), "First line\nsecond line");
+    }
+
+    #[test]
+    fn comment_removed_with_null() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.users (name text);
+            COMMENT ON TABLE public.users IS NULL;
+            COMMENT ON COLUMN public.users.name IS NULL;",
+            &Options::default(),
+        );
+        assert_eq!(
+            nodes[1].sql(),
+            "COMMENT ON TABLE \"public\".\"users\" IS NULL;"
+        );
+        assert_eq!(
+            nodes[2].sql(),
+            "COMMENT ON COLUMN \"public\".\"users\".\"name\" IS NULL;"
+        );
+    }
+
+    #[test]
+    fn psql_meta_commands_stripped() {
+        let dump = "\\restrict abc123
+CREATE TABLE public.users (id int);
+\\set ON_ERROR_STOP on
+\\unrestrict abc123";
+        assert_eq!(
+            strip_meta_commands(dump, &Options::default()),
+            "CREATE TABLE public.users (id int);\n\\set ON_ERROR_STOP on"
+        );
+
+        // Also strip the meta-commands that are asked for
+        let options = Options {
+            meta_commands: vec!["set".to_string()],
+            ..Options::default()
+        };
+        assert_eq!(
+            strip_meta_commands(dump, &options),
+            "CREATE TABLE public.users (id int);"
+        );
+        assert_eq!(get_nodes(dump, &options).len(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use pg_query::NodeEnum;
+
+use crate::options::Options;
+use crate::parse::{
+    excerpt, get_nodes_with_errors, node_kind, split_statements, strip_meta_commands,
+};
+use crate::trace::Tracer;
+
+/// How often a kind of statement occurs in a dump and whether it is supported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindCount {
+    /// The statement kind, with the subtype for statements that have one, e.g.
+    /// `AlterTableStmt AtAddConstraint` or `CommentStmt ObjectTable`
+    pub kind: String,
+    pub handled: usize,
+    /// Statements that are unsupported or fail
+    pub unhandled: usize,
+    /// The start of the first statement of this kind, an unhandled one if there is any
+    pub example: String,
+}
+
+/// Count the kinds of statements in the dump, including the subtypes of `ALTER TABLE` commands
+/// and the object types of comments, grants and the like. Unhandled kinds come first, the most
+/// frequent first, so they can be tackled in order. Statements that fail are counted instead of
+/// aborting, those with a syntax error as `SyntaxError`.
+pub fn survey(sql: &str, options: &Options) -> Vec<KindCount> {
+    let (_, errors) = get_nodes_with_errors(sql, options, &mut Tracer::default());
+
+    // Statements split from another one, e.g. the commands of an `ALTER TABLE`, have the number
+    // of the statement they were split from
+    let mut unhandled: BTreeMap<usize, BTreeSet<String>> = BTreeMap::new();
+    errors.iter().for_each(|e| {
+        unhandled
+            .entry(e.number)
+            .or_default()
+            .extend(statement_kinds(&e.sql));
+    });
+
+    // A dump that can't be split at all is a single statement with a syntax error
+    let sql = strip_meta_commands(sql, options);
+    let statements = split_statements(&sql).unwrap_or_else(|_| vec![sql.as_str()]);
+
+    let mut counts: BTreeMap<String, KindCount> = BTreeMap::new();
+    statements.iter().enumerate().for_each(|(index, sql)| {
+        let failed = unhandled.get(&(index + 1));
+        statement_kinds(sql).into_iter().for_each(|kind| {
+            let is_unhandled = failed.is_some_and(|kinds| kinds.contains(&kind));
+            let count = counts.entry(kind.clone()).or_insert_with(|| KindCount {
+                kind,
+                handled: 0,
+                unhandled: 0,
+                example: excerpt(sql),
+            });
+            if is_unhandled {
+                if count.unhandled == 0 {
+                    count.example = excerpt(sql);
+                }
+                count.unhandled += 1;
+            } else {
+                count.handled += 1;
+            }
+        });
+    });
+
+    let mut counts = counts.into_values().collect::<Vec<_>>();
+    counts.sort_by(|a, b| {
+        b.unhandled
+            .cmp(&a.unhandled)
+            .then((b.handled + b.unhandled).cmp(&(a.handled + a.unhandled)))
+            .then(a.kind.cmp(&b.kind))
+    });
+    counts
+}
+
+/// The kinds of a statement, one per command for an `ALTER TABLE`
+fn statement_kinds(sql: &str) -> Vec<String> {
+    let Ok(result) = pg_query::parse(sql) else {
+        return vec!["SyntaxError".to_string()];
+    };
+
+    result
+        .protobuf
+        .stmts
+        .iter()
+        .filter_map(|s| s.stmt.as_ref()?.node.as_ref())
+        .flat_map(|node| {
+            let kind = node_kind(node);
+            let subtypes = match node {
+                NodeEnum::AlterTableStmt(n) => n
+                    .cmds
+                    .iter()
+                    .filter_map(|c| match &c.node {
+                        Some(NodeEnum::AlterTableCmd(c)) => Some(format!("{:?}", c.subtype())),
+                        _ => None,
+                    })
+                    .collect(),
+                NodeEnum::CommentStmt(n) => vec![format!("{:?}", n.objtype())],
+                NodeEnum::GrantStmt(n) => vec![format!("{:?}", n.objtype())],
+                NodeEnum::DropStmt(n) => vec![format!("{:?}", n.remove_type())],
+                NodeEnum::AlterOwnerStmt(n) => vec![format!("{:?}", n.object_type())],
+                NodeEnum::AlterObjectSchemaStmt(n) => vec![format!("{:?}", n.object_type())],
+                NodeEnum::RenameStmt(n) => vec![format!("{:?}", n.rename_type())],
+                NodeEnum::DefineStmt(n) => vec![format!("{:?}", n.kind())],
+                NodeEnum::SecLabelStmt(n) => vec![format!("{:?}", n.objtype())],
+                _ => Vec::new(),
+            };
+
+            if subtypes.is_empty() {
+                vec![kind]
+            } else {
+                subtypes
+                    .into_iter()
+                    .map(|subtype| format!("{} {}", kind, subtype))
+                    .collect()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use pg_query::NodeEnum;
+
+use crate::locations::StatementLocation;
+use crate::parse::node_kind;
+
+/// A single routing decision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// A statement of the dump, with the variant of the parsed node
+    Statement { sql: String, node: String },
+    /// A lookup of a previously routed object, e.g. the table a comment belongs to
+    Lookup {
+        kind: &'static str,
+        schema: String,
+        name: String,
+        found: bool,
+    },
+    /// The location a statement was routed to
+    Location {
+        category: &'static str,
+        schema: Option<String>,
+        name: String,
+    },
+    /// The file a location was written to
+    Path {
+        category: &'static str,
+        name: String,
+        path: PathBuf,
+    },
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::Statement { sql, node } => write!(f, "{}: {}", node, sql.trim()),
+            TraceEvent::Lookup {
+                kind,
+                schema,
+                name,
+                found,
+            } => write!(
+                f,
+                "  lookup {} {}.{}: {}",
+                kind,
+                schema,
+                name,
+                if *found { "found" } else { "not found" }
+            ),
+            TraceEvent::Location {
+                category,
+                schema: Some(schema),
+                name,
+            } => {
+                write!(f, "  -> {} {}.{}", category, schema, name)
+            }
+            TraceEvent::Location {
+                category,
+                schema: None,
+                name,
+            } => {
+                write!(f, "  -> {}", [*category, name].join(" ").trim_end())
+            }
+            TraceEvent::Path {
+                category,
+                name,
+                path,
+            } => write!(
+                f,
+                "{} written to {}",
+                [*category, name].join(" ").trim_end(),
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Records how statements are routed to files, to debug unexpected placements
+#[derive(Debug, Default)]
+pub struct Tracer {
+    enabled: bool,
+    /// Print events as they are recorded, so the trace survives a panic while routing
+    echo: bool,
+    events: Vec<TraceEvent>,
+    /// Number of nodes whose location has already been recorded
+    located: usize,
+}
+
+impl Tracer {
+    pub fn new(enabled: bool) -> Self {
+        Tracer {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    /// A tracer that also prints every event to stderr
+    pub fn stderr() -> Self {
+        Tracer {
+            enabled: true,
+            echo: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    pub fn statement(&mut self, sql: &str, node: &NodeEnum) {
+        if !self.enabled {
+            return;
+        }
+
+        self.record(TraceEvent::Statement {
+            sql: sql.to_string(),
+            node: node_kind(node),
+        });
+    }
+
+    pub fn lookup(&mut self, kind: &'static str, schema: &str, name: &str, found: bool) {
+        if self.enabled {
+            self.record(TraceEvent::Lookup {
+                kind,
+                schema: schema.to_string(),
+                name: name.to_string(),
+                found,
+            });
+        }
+    }
+
+    /// Records the locations of all nodes added since the last call
+    pub fn locations(&mut self, nodes: &[StatementLocation]) {
+        let new = &nodes[self.located.min(nodes.len())..];
+        self.located = nodes.len();
+
+        if self.enabled {
+            new.iter().for_each(|n| {
+                self.record(TraceEvent::Location {
+                    category: n.category(),
+                    schema: n.schema().map(str::to_string),
+                    name: n.name().to_string(),
+                })
+            });
+        }
+    }
+
+    pub fn path(&mut self, node: &StatementLocation, path: &Path) {
+        if self.enabled {
+            self.record(TraceEvent::Path {
+                category: node.category(),
+                name: node.name().to_string(),
+                path: path.to_path_buf(),
+            });
+        }
+    }
+
+    fn record(&mut self, event: TraceEvent) {
+        if self.echo {
+            eprintln!("{}", event);
+        }
+        self.events.push(event);
+    }
+}
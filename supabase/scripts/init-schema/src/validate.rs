@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+
+use pg_query::NodeEnum;
+use pg_query::protobuf::{KeywordKind, RoleSpecType, Token};
+
+use crate::locations::StatementLocation;
+use crate::parse::{get_sval, parse_sql};
+
+/// Schemas that are managed by Postgres or extensions and never part of the dump
+const SYSTEM_SCHEMAS: &[&str] = &["pg_catalog", "information_schema", "pg_toast", "extensions"];
+
+/// Look for `schema.object` references in function bodies that point into a dumped schema but
+/// do not match any object of the dump, which usually means that the dump is incomplete.
+pub fn validate(nodes: &[StatementLocation]) -> Vec<String> {
+    // Every schema with an object in the dump, as pg_dump never creates `public`
+    let schemas = nodes
+        .iter()
+        .filter_map(|n| n.schema())
+        .filter(|schema| !SYSTEM_SCHEMAS.contains(schema))
+        .collect::<HashSet<_>>();
+
+    let objects = nodes
+        .iter()
+        .filter_map(|n| n.schema().map(|schema| (schema, n.name())))
+        .collect::<HashSet<_>>();
+
+    let mut warnings = Vec::new();
+
+    nodes.iter().for_each(|n| {
+        let (StatementLocation::Function(_) | StatementLocation::TriggerFunction(_)) = n else {
+            return;
+        };
+
+        let Some(body) = function_body(&n.sql()) else {
+            return;
+        };
+
+        qualified_references(&body)
+            .into_iter()
+            .filter(|(schema, _)| schemas.contains(schema.as_str()))
+            .filter(|(schema, name)| !objects.contains(&(schema.as_str(), name.as_str())))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .for_each(|(schema, name)| {
+                warnings.push(format!(
+                    "Function {}.{} references {}.{} which is not part of the dump",
+                    n.schema().unwrap_or_default(),
+                    n.name(),
+                    schema,
+                    name
+                ));
+            });
+    });
+
+    warnings.sort();
+    warnings
+}
+
+/// Find grants to the `PUBLIC` pseudo-role, including default privileges, which give every role
+/// access and are easy to miss in review
+pub fn public_grants(nodes: &[StatementLocation]) -> Vec<String> {
+    nodes
+        .iter()
+        .filter(|n| {
+            let grant = match parse_sql(&n.sql()) {
+                NodeEnum::GrantStmt(g) => g,
+                NodeEnum::AlterDefaultPrivilegesStmt(d) => match d.action {
+                    Some(action) => action,
+                    None => return false,
+                },
+                _ => return false,
+            };
+
+            grant.is_grant
+                && grant.grantees.iter().any(|g| {
+                    matches!(
+                        &g.node,
+                        Some(NodeEnum::RoleSpec(r)) if r.roletype() == RoleSpecType::RolespecPublic
+                    )
+                })
+        })
+        .map(|n| format!("Grant to PUBLIC: {}", n.sql().trim()))
+        .collect()
+}
+
+/// Find files that statements of different objects are written to, e.g. the triggers of a table
+/// that call functions of the same name in different schemas. Statements that share a file by
+/// design, like a table and the sequences it owns, are not reported.
+pub fn path_collisions(nodes: &[StatementLocation]) -> Vec<String> {
+    let mut objects: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    nodes.iter().for_each(|n| {
+        if let Some(object) = file_object(n, nodes) {
+            objects
+                .entry(n.path(Path::new(""), nodes))
+                .or_default()
+                .insert(object);
+        }
+    });
+
+    objects
+        .into_iter()
+        .filter(|(_, objects)| objects.len() > 1)
+        .map(|(path, objects)| {
+            let objects = objects
+                .iter()
+                .map(|(category, schema, name)| match schema {
+                    Some(schema) => format!("{} {}.{}", category, schema, name),
+                    None => format!("{} {}", category, name),
+                })
+                .collect::<Vec<_>>();
+            format!("{} is shared by {}", path.display(), objects.join(", "))
+        })
+        .collect()
+}
+
+/// The object whose file a statement is written to. Files with the statements of many objects,
+/// like the index file of a schema, have none.
+fn file_object(
+    node: &StatementLocation,
+    nodes: &[StatementLocation],
+) -> Option<(&'static str, Option<String>, String)> {
+    let (category, schema, name) = match node.object() {
+        StatementLocation::Schema(_)
+        | StatementLocation::Setup(_)
+        | StatementLocation::Drop(_)
+        | StatementLocation::DefaultPrivileges(_) => return None,
+        // A trigger is written to the file of its trigger function
+        StatementLocation::Trigger(t) => (
+            "TriggerFunction",
+            Some(t.function_schema.as_str()),
+            t.function.clone(),
+        ),
+        StatementLocation::Partition(p) => ("Table", Some(p.schema.as_str()), p.name.clone()),
+        // A sequence is written to the file of the table that owns it, which may only be named by
+        // another statement of the sequence
+        StatementLocation::Sequence(s) => {
+            let owner = nodes.iter().find_map(|n| match n {
+                StatementLocation::Sequence(o) if o.schema == s.schema && o.name == s.name => {
+                    o.owner()
+                }
+                _ => None,
+            });
+            match owner {
+                Some((schema, table)) => ("Table", Some(schema), table.to_string()),
+                None => ("Sequence", Some(s.schema.as_str()), s.name.clone()),
+            }
+        }
+        StatementLocation::UserMapping(m) => ("ForeignServer", None, m.server.clone()),
+        StatementLocation::Policy(p) => (
+            "Policy",
+            Some(p.schema.as_str()),
+            format!("{}.{}", p.table, p.name),
+        ),
+        StatementLocation::ForeignKey(fk) => (
+            "ForeignKey",
+            Some(fk.source_schema.as_str()),
+            format!("{}.{}", fk.source_table, fk.constraint_name),
+        ),
+        StatementLocation::TextSearch(t) => (
+            "TextSearch",
+            Some(t.schema.as_str()),
+            format!("{} {}", t.kind, t.name),
+        ),
+        _ => (node.category(), node.schema(), node.name().to_string()),
+    };
+
+    Some((category, schema.map(str::to_string), name))
+}
+
+/// Extract the body of a `CREATE FUNCTION` statement
+fn function_body(sql: &str) -> Option<String> {
+    let NodeEnum::CreateFunctionStmt(n) = parse_sql(sql) else {
+        return None;
+    };
+
+    n.options.iter().find_map(|o| match &o.node {
+        Some(NodeEnum::DefElem(d)) if d.defname == "as" => {
+            match d.arg.as_ref().and_then(|a| a.node.as_ref()) {
+                Some(NodeEnum::List(l)) => l.items.first().map(|i| get_sval(&i.node)),
+                _ => None,
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Find all `a.b` identifier pairs in a piece of SQL
+fn qualified_references(sql: &str) -> Vec<(String, String)> {
+    // Bodies of non-SQL languages may not be scannable, they are simply skipped
+    let Ok(result) = pg_query::scan(sql) else {
+        return Vec::new();
+    };
+
+    let identifier = |t: &pg_query::protobuf::ScanToken| {
+        if t.token() == Token::Ident || t.keyword_kind() != KeywordKind::NoKeyword {
+            Some(
+                sql[t.start as usize..t.end as usize]
+                    .trim_matches('"')
+                    .to_string(),
+            )
+        } else {
+            None
+        }
+    };
+
+    result
+        .tokens
+        .windows(3)
+        .filter(|w| w[1].token() == Token::Ascii46)
+        .filter_map(|w| Some((identifier(&w[0])?, identifier(&w[2])?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::Options;
+    use crate::parse::get_nodes;
+
+    #[test]
+    fn references_to_objects_missing_from_the_dump() {
+        let nodes = get_nodes(
+            "CREATE SCHEMA api;
+            CREATE TABLE api.users (id int);
+            CREATE TABLE public.posts (id int);
+            CREATE FUNCTION public.f() RETURNS bigint LANGUAGE sql AS $$
+                SELECT count(*) FROM public.posts, public.missing, api.users, api.missing, pg_catalog.pg_class
+            $$;",
+            &Options::default(),
+        );
+        assert_eq!(
+            validate(&nodes),
+            vec![
+                "Function public.f references api.missing which is not part of the dump",
+                "Function public.f references public.missing which is not part of the dump",
+            ]
+        );
+    }
+
+    #[test]
+    fn grants_to_public() {
+        let nodes = get_nodes(
+            "CREATE TABLE public.users (id int);
+            GRANT SELECT ON TABLE public.users TO PUBLIC;
+            GRANT SELECT ON TABLE public.users TO anon;
+            REVOKE ALL ON TABLE public.users FROM PUBLIC;
+            ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT EXECUTE ON FUNCTIONS TO PUBLIC;
+            ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT SELECT ON TABLES TO anon;",
+            &Options::default(),
+        );
+        assert_eq!(
+            public_grants(&nodes),
+            vec![
+                "Grant to PUBLIC: GRANT SELECT ON TABLE public.users TO PUBLIC;",
+                "Grant to PUBLIC: ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT EXECUTE ON FUNCTIONS TO PUBLIC;",
+            ]
+        );
+    }
+}
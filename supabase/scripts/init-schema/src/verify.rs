@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+
+use crate::locations::StatementLocation;
+use crate::options::Options;
+use crate::parse::{get_nodes_with_origins, split_parts, split_statements, strip_meta_commands};
+
+/// Statements of the dump `sql` that are missing from `written`, the content of all generated
+/// files, e.g. because they were lost while routing, deduplicated away or routed to a file that was
+/// not written. A statement that routing splits, e.g. a `GRANT` on several tables, has to be
+/// written in full or in all of its parts. Statements that were skipped or are unsupported are
+/// reported by the routing, and statements of schemas that are not written are left out.
+/// Statements are compared after running them through the deparser, so differences in formatting
+/// and quoting do not count. A statement that routing rewrites as a whole is compared in the form
+/// it was routed.
+pub fn missing_statements(sql: &str, written: &[String], options: &Options) -> Vec<String> {
+    let actual = written
+        .iter()
+        .flat_map(|content| statements(content))
+        .map(|sql| normalize(&sql))
+        .collect::<BTreeSet<_>>();
+
+    let (nodes, origins, issues) = get_nodes_with_origins(sql, options);
+    let skipped = issues
+        .iter()
+        .map(|(number, _, _)| *number)
+        .collect::<BTreeSet<_>>();
+
+    let sql = strip_meta_commands(sql, options);
+    let dump = split_statements(&sql).unwrap_or_else(|_| vec![sql.as_str()]);
+
+    let mut missing: Vec<String> = Vec::new();
+    dump.iter().enumerate().for_each(|(index, statement)| {
+        let number = index + 1;
+        if skipped.contains(&number) {
+            return;
+        }
+
+        let routed = nodes
+            .iter()
+            .zip(&origins)
+            .filter(|(_, origin)| **origin == number)
+            .map(|(n, _)| n)
+            .collect::<Vec<_>>();
+        let (included, excluded): (Vec<&StatementLocation>, Vec<&StatementLocation>) =
+            routed.iter().partition(|n| {
+                n.schema()
+                    .is_none_or(|schema| options.includes_schema(schema))
+            });
+        let excluded = excluded
+            .iter()
+            .map(|n| normalize(&n.sql()))
+            .collect::<BTreeSet<_>>();
+
+        let expected: Vec<String> = if routed.is_empty() {
+            // Lost while routing
+            vec![statement.trim().to_string()]
+        } else if actual.contains(&normalize(statement)) || included.is_empty() {
+            Vec::new()
+        } else if options.extract_inline_fkeys
+            && routed.iter().any(|n| n.category() == "ForeignKey")
+            && routed.iter().any(|n| n.category() == "Table")
+        {
+            // The `CREATE TABLE` is rewritten without its foreign keys
+            included
+                .iter()
+                .map(|n| n.sql().trim().to_string())
+                .collect()
+        } else {
+            let parts: Vec<String> = statements(statement)
+                .iter()
+                .flat_map(|s| split_parts(s))
+                .collect();
+            if parts.len() == 1 && routed.len() == 1 {
+                // Rewritten while routing, e.g. a `COMMENT ON FUNCTION` without its arguments
+                included
+                    .iter()
+                    .map(|n| n.sql().trim().to_string())
+                    .collect()
+            } else {
+                parts
+            }
+        };
+
+        expected.into_iter().for_each(|part| {
+            let normalized = normalize(&part);
+            let part = part.trim().trim_end_matches(';').to_string();
+            if !actual.contains(&normalized)
+                && !excluded.contains(&normalized)
+                && !missing.contains(&part)
+            {
+                missing.push(part);
+            }
+        });
+    });
+
+    missing
+}
+
+/// The statements of a generated file
+pub(crate) fn statements(content: &str) -> Vec<String> {
+    match pg_query::split_with_parser(content) {
+        Ok(statements) => statements.iter().map(|s| s.to_string()).collect(),
+        Err(_) => vec![content.to_string()],
+    }
+}
+
+/// The statement as the deparser prints it, or the trimmed statement if it cannot be parsed
+pub(crate) fn normalize(sql: &str) -> String {
+    pg_query::parse(sql)
+        .and_then(|result| result.deparse())
+        .unwrap_or_else(|_| sql.trim().trim_end_matches(';').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMP: &str = "CREATE TABLE public.a (id int);
+CREATE TABLE public.b (id int);
+CREATE FUNCTION public.f() RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;
+COMMENT ON FUNCTION public.f() IS 'One';
+GRANT ALL ON TABLE public.a, public.b TO anon;";
+
+    fn written(statements: &[&str]) -> Vec<String> {
+        statements.iter().map(|s| format!("{};\n", s)).collect()
+    }
+
+    #[test]
+    fn complete_output() {
+        let written = written(&[
+            "CREATE TABLE public.a (id int)",
+            "GRANT ALL ON TABLE public.a TO anon",
+            "CREATE TABLE public.b (id int)",
+            "GRANT ALL ON TABLE public.b TO anon",
+            "CREATE FUNCTION public.f() RETURNS int LANGUAGE sql AS $$ SELECT 1 $$",
+            "COMMENT ON FUNCTION public.f IS 'One'",
+        ]);
+        assert!(missing_statements(DUMP, &written, &Options::default()).is_empty());
+    }
+
+    #[test]
+    fn lost_statement_and_grant_part() {
+        let written = written(&[
+            "CREATE TABLE public.a (id int)",
+            "GRANT ALL ON TABLE public.a TO anon",
+            "CREATE FUNCTION public.f() RETURNS int LANGUAGE sql AS $$ SELECT 1 $$",
+            "COMMENT ON FUNCTION public.f IS 'One'",
+        ]);
+        assert_eq!(
+            missing_statements(DUMP, &written, &Options::default()),
+            vec![
+                "CREATE TABLE public.b (id int)".to_string(),
+                "GRANT ALL ON public.b TO anon".to_string()
+            ]
+        );
+    }
+}
@@ -1,46 +1,1089 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
     fs,
-    io::prelude::*,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
-use crate::locations::StatementLocation;
+use pg_query::NodeEnum;
 
-pub fn write_nodes(nodes: &[StatementLocation], out_dir: &Path) -> Vec<PathBuf> {
-    nodes
+use crate::layout::LayoutStrategy;
+use crate::locations::{StatementKind, StatementLocation};
+use crate::lock::Lock;
+use crate::manifest::Manifest;
+use crate::options::Options;
+use crate::parse::{deparse_sql, get_sval, leading_comments, parse_sql};
+use crate::trace::Tracer;
+
+/// Name of the file in the output directory that lists the files generated by the last run in
+/// sync mode
+pub const GENERATED_FILES: &str = ".generated";
+
+/// Extension of the pgTAP test stubs written next to functions and tables with policies
+pub const TEST_STUB_EXTENSION: &str = "test.sql";
+
+/// Written above every `CREATE INDEX CONCURRENTLY`, so that tools replaying the files can run
+/// these statements outside of the transaction block of the others
+pub const CONCURRENT_INDEX_MARKER: &str = "-- init-schema: concurrent";
+
+/// Name of the file in every schema directory that includes the files of the schema in order
+pub const SCHEMA_ORDER_FILE: &str = "_order.sql";
+
+/// Destination of the generated files, e.g. the file system or memory
+pub trait SchemaSink {
+    /// Content of a file that exists before the run, if any
+    fn existing(&self, path: &Path) -> Option<String>;
+
+    /// Write the full content of a file, replacing any previous content
+    fn write(&mut self, path: &Path, content: &str);
+
+    /// Remove a file, if it exists
+    fn remove(&mut self, path: &Path);
+}
+
+/// Writes files to disk, creating parent directories as needed
+#[derive(Debug, Default)]
+pub struct FileSink;
+
+impl SchemaSink for FileSink {
+    fn existing(&self, path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+
+    fn write(&mut self, path: &Path, content: &str) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("Failed to create parent directories");
+        }
+        fs::write(path, content).expect("Failed to write to file");
+    }
+
+    fn remove(&mut self, path: &Path) {
+        match fs::remove_file(path) {
+            Ok(()) => {
+                // Clean up directories that only contained this file
+                if let Some(parent) = path.parent() {
+                    let _ = fs::remove_dir(parent);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => panic!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Keeps all files in memory, sorted by path
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub files: BTreeMap<PathBuf, String>,
+}
+
+impl SchemaSink for MemorySink {
+    fn existing(&self, path: &Path) -> Option<String> {
+        self.files.get(path).cloned()
+    }
+
+    fn write(&mut self, path: &Path, content: &str) {
+        self.files.insert(path.to_path_buf(), content.to_string());
+    }
+
+    fn remove(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+}
+
+/// The `SET` statements and `set_config` calls of the dump that the statements of every file rely
+/// on, e.g. `check_function_bodies = false` for functions that use objects created later
+fn session_settings(nodes: &[StatementLocation], options: &Options) -> Vec<String> {
+    let mut settings: Vec<String> = Vec::new();
+    nodes.iter().for_each(|n| {
+        if !matches!(n, StatementLocation::Setup(_)) {
+            return;
+        }
+
+        let is_setting = match parse_sql(&n.sql()) {
+            NodeEnum::VariableSetStmt(_) => true,
+            NodeEnum::SelectStmt(s) => s.target_list.iter().any(|t| match &t.node {
+                Some(NodeEnum::ResTarget(r)) => {
+                    match r.val.as_ref().and_then(|v| v.node.as_ref()) {
+                        Some(NodeEnum::FuncCall(f)) => f
+                            .funcname
+                            .last()
+                            .is_some_and(|n| get_sval(&n.node) == "set_config"),
+                        _ => false,
+                    }
+                }
+                _ => false,
+            }),
+            _ => false,
+        };
+
+        let sql = if options.deparse {
+            deparse_sql(&n.sql())
+        } else {
+            n.sql().trim().to_string()
+        };
+        if is_setting && !settings.contains(&sql) {
+            settings.push(sql);
+        }
+    });
+    settings
+}
+
+/// Write the files for all nodes into `out_dir` on disk
+pub fn write_nodes(
+    nodes: &[StatementLocation],
+    out_dir: &Path,
+    options: &Options,
+    layout: &dyn LayoutStrategy,
+    tracer: &mut Tracer,
+) -> Vec<PathBuf> {
+    write_nodes_to(nodes, out_dir, options, layout, tracer, &mut FileSink)
+}
+
+/// Write the files for all nodes into `sink`, at the paths decided by `layout`. Returns the path
+/// of every node, in sorted order.
+pub fn write_nodes_to(
+    nodes: &[StatementLocation],
+    out_dir: &Path,
+    options: &Options,
+    layout: &dyn LayoutStrategy,
+    tracer: &mut Tracer,
+    sink: &mut impl SchemaSink,
+) -> Vec<PathBuf> {
+    let prefixed_dir;
+    let out_dir = match &options.prefix {
+        Some(prefix) => {
+            prefixed_dir = out_dir.join(prefix);
+            &prefixed_dir
+        }
+        None => out_dir,
+    };
+    let security_dir = out_dir.join("security");
+    let security_dir = security_dir.as_path();
+    let base_dir = move |n: &StatementLocation| {
+        if options.split_security && n.is_security() {
+            security_dir
+        } else {
+            out_dir
+        }
+    };
+
+    // Sort so that statements sharing a file are always written in the same order, independent
+    // of the order of the dump, unless the layout keeps that order. Statements with equal keys,
+    // like the setup statements or the constraints split from one `ALTER TABLE`, are ordered by
+    // their position in the dump, which is unique for every statement, including those split from
+    // a single one.
+    let mut ordered = nodes
         .iter()
-        .map(|n| {
-            let path = n.path(out_dir, nodes);
+        .enumerate()
+        .filter(|(_, n)| {
+            n.schema()
+                .is_none_or(|schema| options.includes_schema(schema))
+        })
+        .collect::<Vec<_>>();
+    if !layout.keep_dump_order() {
+        // Files with a drop or rename keep the order of the dump, see `DumpOrdered`
+        let dump_ordered = nodes
+            .iter()
+            .filter(|n| n.keeps_dump_order())
+            .map(|n| layout.path(n, nodes, base_dir(n)))
+            .collect::<BTreeSet<_>>();
 
-            let content = n.sql();
+        ordered.sort_by_cached_key(|(ordinal, n)| {
+            let key = if !dump_ordered.is_empty()
+                && dump_ordered.contains(&layout.path(n, nodes, base_dir(n)))
+            {
+                (StatementKind::Definition, 0, "")
+            } else {
+                n.sort_key()
+            };
+            (key, *ordinal)
+        });
+    }
+    let mut ordered = ordered.into_iter().map(|(_, n)| n).collect::<Vec<_>>();
+    if options.comments_last {
+        move_comments_last(&mut ordered, nodes, layout, &base_dir);
+    }
 
-            // Create parent directories if they don't exist
-            if let Some(parent) = path.parent() {
-                std::fs::create_dir_all(parent).expect("Failed to create parent directories");
+    let mut manifest = Manifest::default();
+    let mut lock = Lock::default();
+
+    // Content of every file of this run. Files that existed before and must not be touched are
+    // `None`.
+    let mut files: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+    let mut stubs: BTreeMap<PathBuf, String> = BTreeMap::new();
+    let mut setup_files = BTreeSet::new();
+    // Setup statements appended during this run, see below
+    let mut appended_setup: BTreeSet<(PathBuf, String)> = BTreeSet::new();
+
+    let paths: Vec<PathBuf> = ordered
+        .into_iter()
+        .map(|n| {
+            let path = layout.path(n, nodes, base_dir(n));
+            tracer.path(n, &path);
+            if options.emit_test_stubs
+                && let Some(stub) = test_stub(n, nodes)
+            {
+                stubs.insert(path.with_extension(TEST_STUB_EXTENSION), stub);
+            }
+            if options.lock.is_some() {
+                lock.record(n);
             }
+            log::debug!(
+                "{} -> {}",
+                [n.category(), n.name()].join(" ").trim_end(),
+                path.display()
+            );
 
-            // Check if file exists and if content is already in it
-            let file_exists = path.exists();
-            let content_exists = if file_exists {
-                match fs::read_to_string(&path) {
-                    Ok(existing_content) => existing_content.contains(&content),
-                    Err(_) => false,
+            let existing_content = files.entry(path.clone()).or_insert_with(|| {
+                match sink.existing(&path) {
+                    Some(_) if options.append_new_only => None,
+                    // Files are rewritten from scratch, so a re-run yields the same content
+                    _ if options.sync => Some(String::new()),
+                    existing => Some(existing.unwrap_or_default()),
                 }
+            });
+            let Some(existing_content) = existing_content else {
+                return path;
+            };
+
+            // Normalize before comparing so that dedup works on the formatted statements
+            let content = if options.deparse {
+                format!("{}{}", leading_comments(&n.sql()), deparse_sql(&n.sql()))
             } else {
-                false
+                n.sql().trim().to_string()
             };
+            let content = match n {
+                StatementLocation::Index(i) if i.concurrent => {
+                    format!("{}\n{}", CONCURRENT_INDEX_MARKER, content)
+                }
+                _ => content,
+            };
+
+            // Only append if content doesn't already exist. Setup statements are replayed exactly
+            // as they appear in the dump, so a `SET` or `SELECT` repeated within this run is kept
+            // where it is, while the ones a previous run wrote are not appended again.
+            let is_setup = matches!(n, StatementLocation::Setup(_));
+            if is_setup {
+                setup_files.insert(path.clone());
+            }
+            let repeated_setup =
+                is_setup && appended_setup.contains(&(path.clone(), content.clone()));
+            if repeated_setup || !existing_content.contains(&content) {
+                if is_setup {
+                    appended_setup.insert((path.clone(), content.clone()));
+                }
+                // Separate statements within the same file by a blank line
+                if !existing_content.is_empty() {
+                    existing_content.push('\n');
+                }
+                existing_content.push_str(&content);
+                existing_content.push('\n');
 
-            // Only append if content doesn't already exist
-            if !content_exists {
-                let mut file = std::fs::OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(&path)
-                    .expect("Failed to open file");
-                writeln!(file, "{}", content).expect("Failed to write to file");
+                manifest.record(&path, out_dir, n);
             }
 
             path
         })
+        .collect();
+
+    // The file with the setup statements has them already
+    let settings = if options.set_header {
+        session_settings(nodes, options)
+    } else {
+        Vec::new()
+    };
+    let header = if settings.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n\n", settings.join("\n"))
+    };
+    files.iter().for_each(|(path, content)| match content {
+        Some(content) if !setup_files.contains(path) && !content.starts_with(&header) => {
+            sink.write(path, &format!("{}{}", header, content));
+        }
+        Some(content) => sink.write(path, content),
+        None => {}
+    });
+
+    // Stubs are maintained by hand once they exist, so they are not part of the generated files
+    stubs.iter().for_each(|(path, stub)| {
+        if sink.existing(path).is_none() {
+            sink.write(path, stub);
+        }
+    });
+
+    // Every file that belongs to the output of this run, including the ones that were kept
+    let mut generated = files.into_keys().collect::<BTreeSet<_>>();
+
+    if let Some(manifest_path) = &options.manifest {
+        let manifest_path = out_dir.join(manifest_path);
+        sink.write(&manifest_path, &manifest.to_json());
+        generated.insert(manifest_path);
+    }
+
+    if let Some(lock_path) = &options.lock {
+        let lock_path = out_dir.join(lock_path);
+        sink.write(&lock_path, &lock.to_lock());
+        generated.insert(lock_path);
+    }
+
+    // A table that inherits from another one is included after it
+    let parents = nodes
+        .iter()
+        .filter_map(|n| match n {
+            StatementLocation::Table(t) if !t.inherits.is_empty() => Some((n, &t.inherits)),
+            _ => None,
+        })
+        .map(|(n, inherits)| {
+            let parents = inherits
+                .iter()
+                .filter_map(|(schema, name)| {
+                    nodes.iter().find(|p| matches!(p, StatementLocation::Table(t) if t.schema == *schema && t.name == *name))
+                })
+                .map(|p| layout.path(p, nodes, base_dir(p)))
+                .collect::<Vec<_>>();
+            (layout.path(n, nodes, base_dir(n)), parents)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    if options.schema_order && !layout.keep_dump_order() {
+        let files = ordered_files(nodes, options, layout, &base_dir, &parents);
+        generated.extend(write_schema_orders(&files, out_dir, sink));
+    }
+
+    if options.dir_index {
+        generated.extend(write_dir_indexes(&paths, &parents, sink));
+    }
+
+    if options.sync {
+        remove_stale_files(out_dir, &generated, sink);
+    }
+
+    paths
+}
+
+/// Move the comments of every file after its other statements, which only changes files that keep
+/// the order of the dump. A comment is not moved past a drop or rename in its file, after which
+/// the name it uses refers to another object or none.
+fn move_comments_last<'a>(
+    ordered: &mut [&StatementLocation],
+    nodes: &[StatementLocation],
+    layout: &dyn LayoutStrategy,
+    base_dir: &dyn Fn(&StatementLocation) -> &'a Path,
+) {
+    let mut drops: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    let keys = ordered
+        .iter()
+        .map(|n| {
+            let drops = drops.entry(layout.path(n, nodes, base_dir(n))).or_default();
+            if n.keeps_dump_order() {
+                *drops += 1;
+            }
+            (*drops, n.kind() == StatementKind::Comment)
+        })
+        .collect::<Vec<_>>();
+
+    // The sort is stable, so statements that stay in place keep their order
+    let mut keyed = keys
+        .into_iter()
+        .zip(ordered.iter().copied())
+        .collect::<Vec<_>>();
+    keyed.sort_by_key(|(key, _)| *key);
+    ordered
+        .iter_mut()
+        .zip(keyed)
+        .for_each(|(slot, (_, n))| *slot = n);
+}
+
+fn string_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// A pgTAP skeleton for a function or a table with policies
+fn test_stub(node: &StatementLocation, nodes: &[StatementLocation]) -> Option<String> {
+    let assertion = match node {
+        StatementLocation::Function(f) => format!(
+            "SELECT has_function({}, {});",
+            string_literal(&f.schema),
+            string_literal(&f.name)
+        ),
+        StatementLocation::Table(t) => {
+            let policies = nodes
+                .iter()
+                .filter_map(|n| match n {
+                    StatementLocation::Policy(p) if p.schema == t.schema && p.table == t.name => {
+                        Some(string_literal(&p.name))
+                    }
+                    _ => None,
+                })
+                .collect::<BTreeSet<_>>();
+            if policies.is_empty() {
+                return None;
+            }
+
+            format!(
+                "SELECT policies_are({}, {}, ARRAY[{}]);",
+                string_literal(&t.schema),
+                string_literal(&t.name),
+                policies.into_iter().collect::<Vec<_>>().join(", ")
+            )
+        }
+        _ => return None,
+    };
+
+    Some(format!(
+        "BEGIN;\nSELECT plan(1);\n\n-- TODO: replace with real assertions\n{}\n\nSELECT * FROM finish();\nROLLBACK;\n",
+        assertion
+    ))
+}
+
+/// Whether a file is a test stub, which is never deleted
+pub fn is_test_stub(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| {
+        name.to_string_lossy()
+            .ends_with(&format!(".{}", TEST_STUB_EXTENSION))
+    })
+}
+
+/// Write an `__init__.sql` into every directory with statement files that includes those files,
+/// sorted by name. A file comes after the files of `parents` in the same directory, e.g. a table
+/// after the tables it inherits from. Subdirectories have their own `__init__.sql` and are not
+/// included. Returns the paths of the index files.
+fn write_dir_indexes(
+    paths: &[PathBuf],
+    parents: &BTreeMap<PathBuf, Vec<PathBuf>>,
+    sink: &mut impl SchemaSink,
+) -> Vec<PathBuf> {
+    let mut directories: BTreeMap<&Path, BTreeSet<&OsStr>> = BTreeMap::new();
+    paths.iter().for_each(|path| {
+        if let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) {
+            directories.entry(parent).or_default().insert(file_name);
+        }
+    });
+
+    directories
+        .iter()
+        .map(|(dir, files)| {
+            let mut visited = BTreeSet::new();
+            let mut ordered = Vec::new();
+            files.iter().for_each(|f| {
+                include_after_parents(
+                    &dir.join(f),
+                    &|p| p.parent() == Some(dir),
+                    parents,
+                    &mut visited,
+                    &mut ordered,
+                )
+            });
+            let content = ordered
+                .iter()
+                .filter_map(|f| f.file_name())
+                .map(|f| format!("\\ir {}\n", f.to_string_lossy()))
+                .collect::<String>();
+            let index = dir.join("__init__.sql");
+            sink.write(&index, &content);
+            index
+        })
+        .collect()
+}
+
+/// Categories in the order their files are included by `_order.sql`. The definitions are included
+/// in the order of the dump, which `pg_dump` resolved their dependencies in, e.g. of a table on the
+/// function of a column default or of a function on the table it selects from. Constraints come
+/// after all tables and triggers after their functions.
+const ORDER_CATEGORIES: &[&[&str]] = &[
+    &["Schema"],
+    &[
+        "Extension",
+        "EnumNode",
+        "CompositeType",
+        "Collation",
+        "TextSearch",
+        "Sequence",
+        "Table",
+        "Partition",
+        "ForeignTable",
+        "Function",
+        "Aggregate",
+        "Operator",
+        "View",
+    ],
+    &["Index"],
+    &["ForeignKey"],
+    &["TriggerFunction"],
+    &["Trigger"],
+    &["EnablePolicy"],
+    &["Policy"],
+];
+
+/// The files of every schema directory in the order they can be applied in. A file comes with
+/// the earliest category of its statements, files of the same categories in the order of the
+/// dump, but a table always after its `parents`.
+fn ordered_files<'a>(
+    nodes: &[StatementLocation],
+    options: &Options,
+    layout: &dyn LayoutStrategy,
+    base_dir: &dyn Fn(&StatementLocation) -> &'a Path,
+    parents: &BTreeMap<PathBuf, Vec<PathBuf>>,
+) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut keys: BTreeMap<String, BTreeMap<PathBuf, (usize, usize)>> = BTreeMap::new();
+    nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| {
+            n.schema()
+                .is_some_and(|schema| options.includes_schema(schema))
+        })
+        .for_each(|(position, n)| {
+            let base = base_dir(n);
+            let path = layout.path(n, nodes, base);
+            // The schema directory of the file, which is that of the object it belongs to
+            let Some(schema) = path
+                .strip_prefix(base)
+                .ok()
+                .and_then(|p| p.components().next())
+            else {
+                return;
+            };
+            let schema = schema.as_os_str().to_string_lossy().to_string();
+
+            let rank = ORDER_CATEGORIES
+                .iter()
+                .position(|categories| categories.contains(&n.category()))
+                .unwrap_or(ORDER_CATEGORIES.len());
+            let key = keys
+                .entry(schema)
+                .or_default()
+                .entry(path)
+                .or_insert((rank, position));
+            key.0 = key.0.min(rank);
+        });
+
+    keys.into_iter()
+        .map(|(schema, files)| {
+            let mut sorted = files.iter().collect::<Vec<_>>();
+            sorted.sort_by_key(|(_, key)| **key);
+
+            let mut visited = BTreeSet::new();
+            let mut ordered = Vec::new();
+            sorted.into_iter().for_each(|(path, _)| {
+                include_after_parents(
+                    path,
+                    &|p| files.contains_key(p),
+                    parents,
+                    &mut visited,
+                    &mut ordered,
+                )
+            });
+            (schema, ordered)
+        })
         .collect()
 }
+
+/// Write a `_order.sql` into every schema directory that includes the files of the schema via
+/// `\ir`. Returns the paths of the order files.
+fn write_schema_orders(
+    files: &BTreeMap<String, Vec<PathBuf>>,
+    out_dir: &Path,
+    sink: &mut impl SchemaSink,
+) -> Vec<PathBuf> {
+    files
+        .iter()
+        .map(|(schema, paths)| {
+            let schema_dir = out_dir.join(schema);
+            let content = paths
+                .iter()
+                .map(|p| match p.strip_prefix(&schema_dir) {
+                    Ok(relative) => relative.to_path_buf(),
+                    // E.g. the security tree of `--split-security`
+                    Err(_) => Path::new("..").join(p.strip_prefix(out_dir).unwrap_or(p)),
+                })
+                .map(|p| format!("\\ir {}\n", p.display()))
+                .collect::<String>();
+            let path = schema_dir.join(SCHEMA_ORDER_FILE);
+            sink.write(&path, &content);
+            path
+        })
+        .collect()
+}
+
+/// Add `path` to `ordered` after its parents that are `included` and their parents. `visited`
+/// ends cycles, e.g. of tables that share a file.
+fn include_after_parents(
+    path: &Path,
+    included: &dyn Fn(&Path) -> bool,
+    parents: &BTreeMap<PathBuf, Vec<PathBuf>>,
+    visited: &mut BTreeSet<PathBuf>,
+    ordered: &mut Vec<PathBuf>,
+) {
+    if !visited.insert(path.to_path_buf()) {
+        return;
+    }
+
+    parents
+        .get(path)
+        .into_iter()
+        .flatten()
+        .filter(|p| included(p))
+        .for_each(|p| include_after_parents(p, included, parents, visited, ordered));
+    ordered.push(path.to_path_buf());
+}
+
+/// Remove the files of the previous run that this run did not generate, e.g. the file of a table
+/// that was dropped, and record the files of this run for the next one. Only files listed by the
+/// previous run are removed, so files added by hand are left alone.
+fn remove_stale_files(out_dir: &Path, generated: &BTreeSet<PathBuf>, sink: &mut impl SchemaSink) {
+    let list_path = out_dir.join(GENERATED_FILES);
+
+    if let Some(previous) = sink.existing(&list_path) {
+        previous
+            .lines()
+            .map(Path::new)
+            // Never follow a path out of the output directory
+            .filter(|p| p.components().all(|c| matches!(c, Component::Normal(_))))
+            .map(|p| out_dir.join(p))
+            .filter(|p| !generated.contains(p))
+            .for_each(|p| {
+                log::debug!("Removing stale file {}", p.display());
+                sink.remove(&p);
+            });
+    }
+
+    let list = generated
+        .iter()
+        .map(|p| format!("{}\n", p.strip_prefix(out_dir).unwrap_or(p).display()))
+        .collect::<String>();
+    sink.write(&list_path, &list);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{DefaultLayout, FlatLayout};
+    use crate::parse::get_nodes;
+
+    /// The files written for the dump, relative to the output directory
+    fn files(
+        sql: &str,
+        options: &Options,
+        layout: &dyn LayoutStrategy,
+    ) -> BTreeMap<PathBuf, String> {
+        let nodes = get_nodes(sql, options);
+        let mut sink = MemorySink::default();
+        write_nodes_to(
+            &nodes,
+            Path::new(""),
+            options,
+            layout,
+            &mut Tracer::default(),
+            &mut sink,
+        );
+        sink.files
+    }
+
+    #[test]
+    fn drops_next_to_their_create() {
+        let files = files(
+            "CREATE FUNCTION public.add(a int) RETURNS int LANGUAGE sql AS $$ SELECT a + 1 $$;
+CREATE FUNCTION public.touch() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+CREATE TABLE public.t (id int);
+CREATE TRIGGER touch BEFORE UPDATE ON public.t FOR EACH ROW EXECUTE FUNCTION public.touch();
+DROP TRIGGER touch ON public.t RESTRICT;
+DROP FUNCTION public.add(int) CASCADE;",
+            &Options::default(),
+            &DefaultLayout,
+        );
+        assert_eq!(
+            files[Path::new("public/functions/add.sql")],
+            "CREATE FUNCTION public.add(a int) RETURNS int LANGUAGE sql AS $$ SELECT a + 1 $$;
+
+DROP FUNCTION public.add(int) CASCADE;
+"
+        );
+        assert!(files[Path::new("public/triggers/t/touch.sql")].ends_with(
+            "EXECUTE FUNCTION public.touch();\n\nDROP TRIGGER touch ON public.t RESTRICT;\n"
+        ));
+        assert!(!files.keys().any(|path| path.starts_with("drops")));
+    }
+
+    #[test]
+    fn comments_last_in_files_in_dump_order() {
+        let sql = "CREATE TABLE public.a (id int);
+COMMENT ON COLUMN public.a.id IS 'Id of a';
+CREATE TABLE public.b (id int);
+COMMENT ON TABLE public.b IS 'B';
+ALTER TABLE public.b ADD COLUMN name text;
+ALTER TABLE public.b RENAME TO c;
+COMMENT ON TABLE public.c IS 'C';
+CREATE INDEX c_id ON public.c (id);";
+        let options = Options {
+            comments_last: true,
+            ..Options::default()
+        };
+
+        // The column comment follows both tables, but the comment on `b` stays before the rename
+        let flat = files(sql, &options, &FlatLayout);
+        assert_eq!(
+            flat[Path::new("public.sql")],
+            "CREATE TABLE public.a (id int);
+
+CREATE TABLE public.b (id int);
+
+ALTER TABLE public.b ADD COLUMN name text;
+
+COMMENT ON COLUMN \"public\".\"a\".\"id\" IS 'Id of a';
+
+COMMENT ON TABLE \"public\".\"b\" IS 'B';
+
+ALTER TABLE public.b RENAME TO c;
+
+CREATE INDEX c_id ON public.c (id);
+
+COMMENT ON TABLE \"public\".\"c\" IS 'C';
+"
+        );
+
+        let default = files(sql, &options, &DefaultLayout);
+        assert_eq!(
+            default[Path::new("public/tables/c.sql")],
+            "CREATE TABLE public.b (id int);
+
+ALTER TABLE public.b ADD COLUMN name text;
+
+COMMENT ON TABLE \"public\".\"b\" IS 'B';
+
+ALTER TABLE public.b RENAME TO c;
+
+COMMENT ON TABLE \"public\".\"c\" IS 'C';
+"
+        );
+
+        // Without the option, the flat file keeps the order of the dump
+        let flat = files(sql, &Options::default(), &FlatLayout);
+        assert!(flat[Path::new("public.sql")].starts_with(
+            "CREATE TABLE public.a (id int);\n\nCOMMENT ON COLUMN \"public\".\"a\".\"id\" IS 'Id of a';\n"
+        ));
+    }
+
+    #[test]
+    fn setup_statements_keep_their_order() {
+        let sql = "SET statement_timeout = 0;
+SELECT pg_catalog.set_config('search_path', '', false);
+SET check_function_bodies = false;
+CREATE TABLE public.t (id int);
+SET statement_timeout = 0;";
+        let options = Options::default();
+        let nodes = get_nodes(sql, &options);
+        let mut sink = MemorySink::default();
+        write_nodes_to(
+            &nodes,
+            Path::new(""),
+            &options,
+            &DefaultLayout,
+            &mut Tracer::default(),
+            &mut sink,
+        );
+        let index = sink.files[Path::new("index.sql")].clone();
+        assert_eq!(
+            index,
+            "SET statement_timeout = 0;
+
+SELECT pg_catalog.set_config('search_path', '', false);
+
+SET check_function_bodies = false;
+
+SET statement_timeout = 0;
+"
+        );
+
+        // A re-run over the same output does not append the statements again
+        write_nodes_to(
+            &nodes,
+            Path::new(""),
+            &options,
+            &DefaultLayout,
+            &mut Tracer::default(),
+            &mut sink,
+        );
+        assert_eq!(sink.files[Path::new("index.sql")], index);
+    }
+
+    #[test]
+    fn owner_named_like_a_reserved_word() {
+        let sql = "CREATE TABLE public.t (id int);
+ALTER TABLE public.t OWNER TO \"user\";
+CREATE FUNCTION public.f() RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;
+ALTER FUNCTION public.f() OWNER TO \"user\";";
+        [false, true].into_iter().for_each(|deparse| {
+            let options = Options {
+                keep_owners: true,
+                deparse,
+                ..Options::default()
+            };
+            let files = files(sql, &options, &DefaultLayout);
+            ["public/tables/t.sql", "public/functions/f.sql"]
+                .into_iter()
+                .for_each(|path| {
+                    let content = &files[Path::new(path)];
+                    assert!(content.contains("OWNER TO \"user\";"), "{}", content);
+                });
+        });
+    }
+
+    #[test]
+    fn same_output_on_every_run() {
+        let sql = "SET statement_timeout = 0;
+CREATE SCHEMA api;
+CREATE TABLE api.users (id int, name text);
+CREATE FUNCTION api.touch() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+GRANT SELECT ON TABLE api.users TO anon;
+COMMENT ON COLUMN api.users.name IS 'Name';
+CREATE INDEX users_name ON api.users (name);
+CREATE TRIGGER touch BEFORE UPDATE ON api.users FOR EACH ROW EXECUTE FUNCTION api.touch();
+ALTER TABLE api.users ENABLE ROW LEVEL SECURITY;
+CREATE POLICY read ON api.users FOR SELECT USING (true);
+COMMENT ON TABLE api.users IS 'Users';";
+        let first = files(sql, &Options::default(), &DefaultLayout);
+        let second = files(sql, &Options::default(), &DefaultLayout);
+        assert_eq!(first, second);
+        assert!(first.len() > 5);
+    }
+
+    #[test]
+    fn default_using_a_sequence_of_another_schema() {
+        let sql = "CREATE SCHEMA shared;
+CREATE SEQUENCE shared.seq;
+CREATE TABLE public.t (id int);
+ALTER TABLE ONLY public.t ALTER COLUMN id SET DEFAULT nextval('shared.seq'::regclass);";
+        let with_schemas = |schemas: &[&str]| Options {
+            schemas: schemas.iter().map(|s| s.to_string()).collect(),
+            schema_order: true,
+            ..Options::default()
+        };
+
+        // The sequence stays in its schema, which is applied before the table's
+        let all = files(sql, &with_schemas(&[]), &DefaultLayout);
+        assert_eq!(
+            all[Path::new("shared/sequences/seq.sql")],
+            "CREATE SEQUENCE shared.seq;\n"
+        );
+        assert!(!all[Path::new("public/tables/t.sql")].contains("CREATE SEQUENCE"));
+        assert!(all[Path::new("shared/_order.sql")].contains("sequences/seq.sql"));
+        assert!(all[Path::new("public/_order.sql")].contains("tables/t.sql"));
+
+        let shared = files(sql, &with_schemas(&["shared"]), &DefaultLayout);
+        assert!(shared.contains_key(Path::new("shared/sequences/seq.sql")));
+        assert!(!shared.keys().any(|path| path.starts_with("public")));
+
+        let public = files(sql, &with_schemas(&["public"]), &DefaultLayout);
+        assert!(
+            public[Path::new("public/tables/t.sql")].contains("nextval('shared.seq'::regclass)")
+        );
+        assert!(!public.keys().any(|path| path.starts_with("shared")));
+    }
+
+    #[test]
+    fn sections_of_a_table_file() {
+        let files = files(
+            "CREATE TABLE public.users (id int NOT NULL, email text);
+COMMENT ON TABLE public.users IS 'Users';
+GRANT SELECT ON TABLE public.users TO anon;
+ALTER TABLE public.users ENABLE ROW LEVEL SECURITY;
+COMMENT ON COLUMN public.users.email IS 'Email';
+ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+GRANT ALL ON TABLE public.users TO authenticated;
+ALTER TABLE ONLY public.users ADD CONSTRAINT users_email_key UNIQUE (email);",
+            &Options::default(),
+            &DefaultLayout,
+        );
+        assert_eq!(
+            files[Path::new("public/tables/users.sql")],
+            "CREATE TABLE public.users (id int NOT NULL, email text);
+
+ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.users ADD CONSTRAINT users_email_key UNIQUE (email);
+
+GRANT SELECT ON TABLE public.users TO anon;
+
+GRANT ALL ON TABLE public.users TO authenticated;
+
+COMMENT ON TABLE \"public\".\"users\" IS 'Users';
+
+COMMENT ON COLUMN \"public\".\"users\".\"email\" IS 'Email';
+"
+        );
+        // Row level security has a file of its own next to the policies
+        assert_eq!(
+            files[Path::new("public/policies/users/enable_rls.sql")],
+            "ALTER TABLE public.users ENABLE ROW LEVEL SECURITY;\n"
+        );
+    }
+
+    #[test]
+    fn grants_and_policies_in_the_security_tree() {
+        let options = Options {
+            split_security: true,
+            ..Options::default()
+        };
+        let files = files(
+            "CREATE TABLE public.users (id int);
+GRANT SELECT ON TABLE public.users TO anon;
+ALTER TABLE public.users ENABLE ROW LEVEL SECURITY;
+CREATE POLICY read ON public.users FOR SELECT USING (true);
+COMMENT ON TABLE public.users IS 'Users';",
+            &options,
+            &DefaultLayout,
+        );
+        assert_eq!(
+            files.keys().map(PathBuf::as_path).collect::<Vec<_>>(),
+            vec![
+                Path::new("public/tables/users.sql"),
+                Path::new("security/public/policies/users/enable_rls.sql"),
+                Path::new("security/public/policies/users/read.sql"),
+                Path::new("security/public/tables/users.sql"),
+            ]
+        );
+        assert_eq!(
+            files[Path::new("public/tables/users.sql")],
+            "CREATE TABLE public.users (id int);\n\nCOMMENT ON TABLE \"public\".\"users\" IS 'Users';\n"
+        );
+        assert_eq!(
+            files[Path::new("security/public/tables/users.sql")],
+            "GRANT SELECT ON TABLE public.users TO anon;\n"
+        );
+    }
+
+    #[test]
+    fn dir_index_sorted_by_name() {
+        let options = Options {
+            dir_index: true,
+            ..Options::default()
+        };
+        let files = files(
+            "CREATE TABLE public.users (id int);
+CREATE TABLE public.accounts (id int);
+CREATE TABLE public.memberships (id int);
+CREATE FUNCTION public.add(a int) RETURNS int LANGUAGE sql AS $$ SELECT a + 1 $$;",
+            &options,
+            &DefaultLayout,
+        );
+        assert_eq!(
+            files[Path::new("public/tables/__init__.sql")],
+            "\\ir accounts.sql\n\\ir memberships.sql\n\\ir users.sql\n"
+        );
+        assert_eq!(
+            files[Path::new("public/functions/__init__.sql")],
+            "\\ir add.sql\n"
+        );
+    }
+
+    #[test]
+    fn dir_index_with_a_table_after_its_parent() {
+        let options = Options {
+            dir_index: true,
+            ..Options::default()
+        };
+        let files = files(
+            "CREATE TABLE public.z (id int);
+CREATE TABLE public.b (name text) INHERITS (public.z);
+CREATE TABLE public.a (id int);",
+            &options,
+            &DefaultLayout,
+        );
+        assert_eq!(
+            files[Path::new("public/tables/__init__.sql")],
+            "\\ir a.sql\n\\ir z.sql\n\\ir b.sql\n"
+        );
+    }
+
+    #[test]
+    fn constraints_split_from_one_statement_keep_their_order() {
+        let sql = "CREATE TABLE public.t (id int, code text);
+ALTER TABLE ONLY public.t
+    ADD CONSTRAINT t_id_check CHECK (id > 0),
+    ADD CONSTRAINT a_code_key UNIQUE (code);";
+        let expected = "CREATE TABLE public.t (id int, code text);
+
+ALTER TABLE ONLY public.t ADD CONSTRAINT t_id_check CHECK (id > 0);
+
+ALTER TABLE ONLY public.t ADD CONSTRAINT a_code_key UNIQUE (code);
+";
+        // The constraints have equal sort keys, so they keep the order of the commands, not of
+        // their names, on every run
+        (0..2).for_each(|_| {
+            let files = files(sql, &Options::default(), &DefaultLayout);
+            assert_eq!(files[Path::new("public/tables/t.sql")], expected);
+        });
+    }
+
+    #[test]
+    fn schema_order_file() {
+        let options = Options {
+            schema_order: true,
+            ..Options::default()
+        };
+        let sql = "CREATE TABLE public.users (id int, team_id int);
+CREATE TABLE public.teams (id int PRIMARY KEY);
+CREATE FUNCTION public.touch() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+CREATE INDEX users_team_id ON public.users (team_id);
+ALTER TABLE ONLY public.users ADD CONSTRAINT users_team_id_fkey FOREIGN KEY (team_id) REFERENCES public.teams(id);
+CREATE TRIGGER touch BEFORE UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION public.touch();
+CREATE TRIGGER touch BEFORE UPDATE ON public.teams FOR EACH ROW EXECUTE FUNCTION public.touch();";
+        // Tables in the order of the dump, then their indices and foreign keys, then the trigger
+        // function before the triggers that call it
+        let first = files(sql, &options, &DefaultLayout);
+        assert_eq!(
+            first[Path::new("public/_order.sql")],
+            "\\ir tables/users.sql
+\\ir tables/teams.sql
+\\ir indices/users/users_team_id.sql
+\\ir fkeys/users/users_team_id_fkey.sql
+\\ir triggers/touch.sql
+\\ir triggers/users/touch.sql
+\\ir triggers/teams/touch.sql
+"
+        );
+
+        // A re-run over the output writes the same files
+        let nodes = get_nodes(sql, &options);
+        let mut sink = MemorySink {
+            files: first.clone(),
+        };
+        write_nodes_to(
+            &nodes,
+            Path::new(""),
+            &options,
+            &DefaultLayout,
+            &mut Tracer::default(),
+            &mut sink,
+        );
+        assert_eq!(sink.files, first);
+    }
+
+    #[test]
+    fn two_levels_of_inheritance() {
+        let options = Options {
+            dir_index: true,
+            schema_order: true,
+            ..Options::default()
+        };
+        // Neither the names nor the order of the statements put the parents first
+        let files = files(
+            "CREATE TABLE public.alpha (extra text) INHERITS (public.mid);
+CREATE TABLE public.mid (name text) INHERITS (public.zeta);
+CREATE TABLE public.zeta (id int);",
+            &options,
+            &DefaultLayout,
+        );
+        assert_eq!(
+            files[Path::new("public/_order.sql")],
+            "\\ir tables/zeta.sql\n\\ir tables/mid.sql\n\\ir tables/alpha.sql\n"
+        );
+        assert_eq!(
+            files[Path::new("public/tables/__init__.sql")],
+            "\\ir zeta.sql\n\\ir mid.sql\n\\ir alpha.sql\n"
+        );
+    }
+}